@@ -0,0 +1,103 @@
+use aoc::intcode::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+// Sums 1..=n with a relative-base accumulator, so every instruction kind runs
+const SUM_LOOP: &str = "
+            in [n]
+            arb #acc
+    loop:   add [bp+0], [i], [bp+0]
+            add [i], #1, [i]
+            lt [n], [i], [t]
+            jf [t], #loop
+            out [bp+0]
+            hlt
+    n:      .data 0
+    i:      .data 1
+    t:      .data 0
+    acc:    .data 0
+";
+
+// Multiplies its inputs by repeated addition and compares the product against a
+// threshold, like the 2019 day 19 drone program that is started afresh per probe
+const PROBE: &str = "
+            in [x]
+            in [y]
+    mul:    add [p], [x], [p]
+            add [y], #-1, [y]
+            lt #0, [y], [t]
+            jt [t], #mul
+            lt [p], #1000, [t]
+            jt [t], #inside
+            out #0
+            hlt
+    inside: out #1
+            hlt
+    x:      .data 0
+    y:      .data 0
+    p:      .data 0
+    t:      .data 0
+";
+
+fn run(tape: &Tape, input: &[isize], cached: bool) -> isize {
+    let mut machine = IntcodeMachine::new_io(
+        tape.clone(),
+        new_stream_ref_from_iter(input.iter().copied()),
+        new_stream_ref(),
+    );
+    machine.set_decode_cache(cached);
+    machine.run_to_completion().unwrap();
+    let output = machine.output.borrow_mut().pop_back().unwrap();
+    output
+}
+
+fn bench_sum_loop(c: &mut Criterion) {
+    let tape = assemble(SUM_LOOP).unwrap();
+    let mut group = c.benchmark_group("sum_loop");
+    for cached in [false, true] {
+        let id = BenchmarkId::new(if cached { "cached" } else { "decoding" }, 100_000);
+        group.bench_with_input(id, &cached, |b, &cached| {
+            b.iter(|| run(&tape, black_box(&[100_000]), cached))
+        });
+    }
+    group.finish();
+}
+
+fn run_decoded(decoded: &DecodedTape, input: &[isize]) -> isize {
+    let mut machine: IntcodeMachine = IntcodeMachine::from_decoded(
+        decoded,
+        new_stream_ref_from_iter(input.iter().copied()),
+        new_stream_ref(),
+    );
+    machine.run_to_completion().unwrap();
+    let output = machine.output.borrow_mut().pop_back().unwrap();
+    output
+}
+
+fn probe_grid(mut probe: impl FnMut(&[isize]) -> isize) -> isize {
+    let mut inside = 0;
+    for y in 0..50 {
+        for x in 0..50 {
+            inside += probe(black_box(&[x, y]));
+        }
+    }
+    inside
+}
+
+fn bench_fresh_machines(c: &mut Criterion) {
+    let tape = assemble(PROBE).unwrap();
+    let mut group = c.benchmark_group("fresh_machines");
+    for cached in [false, true] {
+        let id = BenchmarkId::new(if cached { "cached" } else { "decoding" }, 2500);
+        group.bench_with_input(id, &cached, |b, &cached| {
+            b.iter(|| probe_grid(|input| run(&tape, input, cached)))
+        });
+    }
+    let decoded = DecodedTape::new(tape);
+    group.bench_function(BenchmarkId::new("shared", 2500), |b| {
+        b.iter(|| probe_grid(|input| run_decoded(&decoded, input)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_sum_loop, bench_fresh_machines);
+criterion_main!(benches);
@@ -1,55 +1,741 @@
-use reqwest::blocking::Client;
-use reqwest::header::{HeaderMap, COOKIE};
+pub mod cache;
+pub mod options;
+pub mod puzzle;
+pub mod submit;
+pub mod throttle;
+pub mod transport;
+
+#[cfg(test)]
+pub(crate) mod test_server;
+
+use crate::calendar::{self, CalendarError};
+use cache::Cache;
+use options::InputOptions;
+use puzzle::PuzzlePage;
 use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
+use std::fmt;
+use std::fmt::Display;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use submit::{encode_form_value, normalize_answer, Ledger, SubmitOutcome, Verdict};
+use throttle::{RateLimiter, RetryPolicy};
+use transport::{HttpRequest, HttpResponse, HttpTransport, ReqwestTransport};
 
-const CACHE_DIR: &str = "input_cache";
+const DEFAULT_BASE_URL: &str = "https://adventofcode.com";
+const DEFAULT_USER_AGENT: &str = "github.com/ibookstein/aoc";
+const USER_AGENT_ENV_VAR: &str = "AOC_USER_AGENT";
+const REQUEST_LOCK_FILE: &str = "aoc_request.lock";
 const SESSION_FILE_PATH: &[&str] = &["..", "..", "session.txt"];
+const SESSION_ENV_VAR: &str = "AOC_SESSION";
+const CONFIG_FILE_ENV_VAR: &str = "AOC_CONFIG";
+const CONFIG_SESSION_KEY: &str = "session";
+const NOT_UNLOCKED_MARKER: &str = "before it unlocks";
+const LEDGER_DIR: &str = "answers";
+
+#[derive(Debug)]
+pub enum InputError {
+    MissingSession,
+    InvalidSession,
+    NotYetUnlocked(Duration),
+    UnexpectedContent,
+    InvalidPuzzle(u16, u8),
+    InvalidPart(u8),
+    InvalidAnswer(String),
+    InvalidOptions(&'static str),
+    CorruptLedger(PathBuf, usize),
+    Offline,
+    HttpStatus(u16),
+    Http(reqwest::Error),
+    CacheIo(std::io::Error),
+    InputOverride(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::MissingSession => write!(f, "No session key found"),
+            InputError::InvalidSession => write!(f, "Session key was rejected"),
+            InputError::NotYetUnlocked(remaining) => write!(
+                f,
+                "Puzzle is not unlocked yet, {} seconds remaining",
+                remaining.as_secs()
+            ),
+            InputError::UnexpectedContent => write!(f, "Response does not look like puzzle input"),
+            InputError::InvalidPuzzle(year, day) => {
+                write!(f, "There is no puzzle for {} day {}", year, day)
+            }
+            InputError::InvalidPart(part) => write!(f, "Invalid puzzle part {}", part),
+            InputError::InvalidAnswer(answer) => write!(f, "Invalid answer {:?}", answer),
+            InputError::InvalidOptions(reason) => write!(f, "Invalid input options: {}", reason),
+            InputError::CorruptLedger(path, line) => {
+                write!(
+                    f,
+                    "Corrupt answer ledger {} at line {}",
+                    path.display(),
+                    line
+                )
+            }
+            InputError::Offline => write!(f, "Offline mode and the request is not cached"),
+            InputError::HttpStatus(status) => write!(f, "Unexpected HTTP status {}", status),
+            InputError::Http(e) => write!(f, "HTTP error: {}", e),
+            InputError::CacheIo(e) => write!(f, "Cache I/O error: {}", e),
+            InputError::InputOverride(e) => write!(f, "Failed reading input override: {}", e),
+            InputError::Json(e) => write!(f, "Invalid JSON: {}", e),
+        }
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InputError::Http(e) => Some(e),
+            InputError::CacheIo(e) => Some(e),
+            InputError::InputOverride(e) => Some(e),
+            InputError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for InputError {
+    fn from(e: reqwest::Error) -> Self {
+        InputError::Http(e)
+    }
+}
+
+impl From<CalendarError> for InputError {
+    fn from(e: CalendarError) -> Self {
+        match e {
+            CalendarError::InvalidYear(year) => InputError::InvalidPuzzle(year, 0),
+            CalendarError::InvalidDay(year, day) => InputError::InvalidPuzzle(year, day),
+            CalendarError::NotYetUnlocked(remaining) => InputError::NotYetUnlocked(remaining),
+        }
+    }
+}
+
+impl From<std::io::Error> for InputError {
+    fn from(e: std::io::Error) -> Self {
+        InputError::CacheIo(e)
+    }
+}
+
+pub type InputResult<T> = Result<T, InputError>;
 
-fn get_session_key() -> String {
-    let mut path = std::env::current_exe().unwrap();
+#[derive(Debug, Clone)]
+pub enum SessionSource {
+    Key(String),
+    Env(String),
+    File(PathBuf),
+    ConfigFile(PathBuf),
+}
+
+fn read_optional_file(path: &PathBuf) -> InputResult<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_config_session(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == CONFIG_SESSION_KEY)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+}
+
+impl SessionSource {
+    fn resolve(&self) -> InputResult<Option<String>> {
+        let key = match self {
+            SessionSource::Key(key) => Some(key.clone()),
+            SessionSource::Env(var) => std::env::var(var).ok(),
+            SessionSource::File(path) => read_optional_file(path)?,
+            SessionSource::ConfigFile(path) => {
+                read_optional_file(path)?.and_then(|content| parse_config_session(&content))
+            }
+        };
+
+        Ok(key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty()))
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_FILE_ENV_VAR) {
+        return Some(PathBuf::from(path));
+    }
+
+    let mut path = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut home = PathBuf::from(std::env::var_os("HOME")?);
+            home.push(".config");
+            home
+        }
+    };
+    path.push("aoc");
+    path.push("config");
+    Some(path)
+}
+
+fn exe_relative_session_path() -> Option<PathBuf> {
+    let mut path = std::env::current_exe().ok()?;
     path.pop();
-    path.push(&SESSION_FILE_PATH.iter().cloned().collect::<PathBuf>());
+    path.push(SESSION_FILE_PATH.iter().collect::<PathBuf>());
+    Some(path)
+}
+
+fn default_session_sources() -> Vec<SessionSource> {
+    let mut sources = vec![SessionSource::Env(SESSION_ENV_VAR.to_string())];
+    sources.extend(default_config_path().map(SessionSource::ConfigFile));
+    sources.extend(exe_relative_session_path().map(SessionSource::File));
+    sources
+}
+
+#[derive(Debug, Clone)]
+pub struct InputProvider {
+    session_sources: Vec<SessionSource>,
+    cache: Cache,
+    refresh: bool,
+    offline: bool,
+    base_url: String,
+    user_agent: String,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl Default for InputProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputProvider {
+    pub fn new() -> Self {
+        InputProvider {
+            session_sources: default_session_sources(),
+            cache: Cache::new(cache::default_dir()),
+            refresh: false,
+            offline: false,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            user_agent: std::env::var(USER_AGENT_ENV_VAR)
+                .unwrap_or_else(|_| DEFAULT_USER_AGENT.to_string()),
+            rate_limiter: RateLimiter::default()
+                .with_lock_file(std::env::temp_dir().join(REQUEST_LOCK_FILE)),
+            retry_policy: RetryPolicy::default(),
+            transport: Arc::new(ReqwestTransport::new()),
+        }
+    }
+
+    pub fn with_session(mut self, source: SessionSource) -> Self {
+        self.session_sources = vec![source];
+        self
+    }
+
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache = Cache::new(cache_dir);
+        self
+    }
+
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn session_key(&self) -> InputResult<String> {
+        for source in self.session_sources.iter() {
+            if let Some(key) = source.resolve()? {
+                return Ok(key);
+            }
+        }
+        Err(InputError::MissingSession)
+    }
+
+    fn send(&self, request: HttpRequest) -> InputResult<HttpResponse> {
+        if self.offline {
+            return Err(InputError::Offline);
+        }
+
+        let cookie = format!("session={}", self.session_key()?);
+        let request = request
+            .with_header("Cookie", cookie)
+            .with_header("User-Agent", self.user_agent.clone());
+
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.wait()?;
+            let resp = self.transport.send(&request)?;
+            if !self.retry_policy.should_retry(attempt, resp.status) {
+                return Ok(resp);
+            }
+
+            std::thread::sleep(self.retry_policy.backoff(attempt));
+            attempt += 1;
+        }
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    fn load_cached(&self, name: &str) -> InputResult<Option<String>> {
+        if self.refresh && !self.offline {
+            self.cache.invalidate(name)?;
+        }
+        self.cache.load(name)
+    }
+
+    fn get_input_web(&self, year: u16, day: u8) -> InputResult<HttpResponse> {
+        calendar::check_unlocked(year, day)?;
+        let url = format!("{}/{}/day/{}/input", self.base_url, year, day);
+        let resp = self.send(HttpRequest::get(url))?;
+
+        match resp.status {
+            200 if resp.body.trim_start().starts_with('<') => Err(InputError::UnexpectedContent),
+            200 => Ok(resp),
+            400 => Err(InputError::InvalidSession),
+            404 if resp.body.contains(NOT_UNLOCKED_MARKER) => Err(not_yet_unlocked(year, day)),
+            status => Err(InputError::HttpStatus(status)),
+        }
+    }
+
+    pub(crate) fn get_path(&self, path: &str) -> InputResult<HttpResponse> {
+        let url = format!("{}{}", self.base_url, path);
+        let resp = self.send(HttpRequest::get(url))?;
 
-    let mut content = String::new();
-    let mut file = File::open(path).unwrap();
-    file.read_to_string(&mut content).unwrap();
-    content.trim().to_string()
+        match resp.status {
+            200 => Ok(resp),
+            400 => Err(InputError::InvalidSession),
+            status => Err(InputError::HttpStatus(status)),
+        }
+    }
+
+    fn get_puzzle_web(&self, year: u16, day: u8) -> InputResult<HttpResponse> {
+        calendar::check_unlocked(year, day)?;
+        let url = format!("{}/{}/day/{}", self.base_url, year, day);
+        let resp = self.send(HttpRequest::get(url))?;
+
+        match resp.status {
+            200 => Ok(resp),
+            404 if resp.body.contains(NOT_UNLOCKED_MARKER) => Err(not_yet_unlocked(year, day)),
+            status => Err(InputError::HttpStatus(status)),
+        }
+    }
+
+    pub fn get_puzzle_html(&self, year: u16, day: u8) -> InputResult<String> {
+        calendar::validate(year, day)?;
+        let name = puzzle_cache_name(year, day);
+        if let Some(html) = self.load_cached(&name)? {
+            return Ok(html);
+        }
+
+        let resp = self.get_puzzle_web(year, day)?;
+        self.cache.store(&name, &resp)?;
+        Ok(resp.body)
+    }
+
+    pub fn get_puzzle_page(&self, year: u16, day: u8) -> InputResult<PuzzlePage> {
+        Ok(PuzzlePage::parse(&self.get_puzzle_html(year, day)?))
+    }
+
+    pub fn ledger(&self, year: u16, day: u8) -> InputResult<Ledger> {
+        let mut path = self.cache.dir().join(LEDGER_DIR);
+        path.push(format!("{}_{}.txt", year, day));
+        Ledger::load(path)
+    }
+
+    pub fn submit(
+        &self,
+        year: u16,
+        day: u8,
+        part: u8,
+        answer: impl Display,
+    ) -> InputResult<SubmitOutcome> {
+        if !(1..=2).contains(&part) {
+            return Err(InputError::InvalidPart(part));
+        }
+        calendar::check_unlocked(year, day)?;
+
+        let answer = answer.to_string();
+        let answer = normalize_answer(&answer)?;
+        let mut ledger = self.ledger(year, day)?;
+        if let Some(rejection) = ledger.check(part, answer) {
+            return Ok(SubmitOutcome::Rejected(rejection));
+        }
+
+        let url = format!("{}/{}/day/{}/answer", self.base_url, year, day);
+        let body = format!("level={}&answer={}", part, encode_form_value(answer));
+        let resp = self.send(HttpRequest::post_form(url, body))?;
+
+        let verdict = match resp.status {
+            200 => Verdict::parse(&resp.body),
+            400 => return Err(InputError::InvalidSession),
+            status => return Err(InputError::HttpStatus(status)),
+        };
+
+        ledger.record(part, answer, verdict.clone())?;
+        if verdict == Verdict::Correct {
+            self.cache.invalidate(&puzzle_cache_name(year, day))?;
+        }
+        Ok(SubmitOutcome::Submitted(verdict))
+    }
+
+    pub fn invalidate(&self, year: u16, day: u8) -> InputResult<bool> {
+        self.cache.invalidate(&input_cache_name(year, day))
+    }
+
+    pub fn get_input(&self, year: u16, day: u8) -> InputResult<String> {
+        calendar::validate(year, day)?;
+        let name = input_cache_name(year, day);
+        if let Some(input) = self.load_cached(&name)? {
+            println!("Cache hit for {}", &name);
+            return Ok(input);
+        }
+
+        println!("Cache miss for {}", &name);
+        let resp = self.get_input_web(year, day)?;
+        self.cache.store(&name, &resp)?;
+        Ok(resp.body)
+    }
+
+    pub fn get_input_when_unlocked(&self, year: u16, day: u8) -> InputResult<String> {
+        calendar::wait_until_unlock(year, day)?;
+        self.get_input(year, day)
+    }
 }
 
-fn get_input_web(year: u16, day: u8) -> Result<String, Box<dyn Error>> {
-    let url_str = format!("https://adventofcode.com/{}/day/{}/input", year, day);
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        COOKIE,
-        format!("session={}", get_session_key()).parse().unwrap(),
-    );
-    let resp = Client::new().get(&url_str).headers(headers).send()?;
-    Ok(resp.text()?)
+fn not_yet_unlocked(year: u16, day: u8) -> InputError {
+    InputError::NotYetUnlocked(calendar::time_until_unlock(year, day).unwrap_or_default())
 }
 
-fn try_get_input(year: u16, day: u8) -> Result<String, Box<dyn Error>> {
-    let mut path = PathBuf::new();
-    path.push(std::env::current_exe()?.parent().unwrap());
-    path.push(CACHE_DIR);
-    let filename = format!("{}_{}.txt", year, day);
-    path.push(&filename);
+fn input_cache_name(year: u16, day: u8) -> String {
+    format!("{}_{}.txt", year, day)
+}
 
-    let _ = std::fs::create_dir(path.parent().unwrap());
+fn puzzle_cache_name(year: u16, day: u8) -> String {
+    format!("{}_{}.html", year, day)
+}
 
-    if path.exists() {
-        println!("Cache hit for {}", &filename);
-        Ok(std::fs::read_to_string(path)?)
-    } else {
-        println!("Cache miss for {}", &filename);
-        let input_str = get_input_web(year, day)?;
-        std::fs::write(path, &input_str)?;
-        Ok(input_str)
+pub fn try_get_input(year: u16, day: u8) -> InputResult<String> {
+    let options = InputOptions::from_env()?;
+    if let Some(input) = options.read_override()? {
+        return Ok(input);
     }
+    options.apply(InputProvider::new()).get_input(year, day)
+}
+
+pub fn submit(year: u16, day: u8, part: u8, answer: impl Display) -> InputResult<SubmitOutcome> {
+    let options = InputOptions::from_env()?;
+    options
+        .apply(InputProvider::new())
+        .submit(year, day, part, answer)
 }
 
 pub fn get_input(year: u16, day: u8) -> String {
     try_get_input(year, day).expect("Failed getting input")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::test_server::{scratch_dir, StubServer};
+    use super::transport::FakeTransport;
+    use super::*;
+    use std::time::Duration;
+
+    const INPUT_URL: &str = "http://fake/2019/day/1/input";
+
+    fn test_provider(name: &str, base_url: &str) -> InputProvider {
+        InputProvider::new()
+            .with_session(SessionSource::Key("cafe".to_string()))
+            .with_cache_dir(scratch_dir(name))
+            .with_base_url(base_url)
+            .with_rate_limiter(RateLimiter::disabled())
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+            })
+    }
+
+    fn fake_provider(name: &str, transport: Arc<FakeTransport>) -> InputProvider {
+        test_provider(name, "http://fake/").with_transport(transport)
+    }
+
+    #[test]
+    fn test_cache_miss_then_hit() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(INPUT_URL, HttpResponse::new(200, "12\n14\n"));
+        let provider = fake_provider("miss_then_hit", transport.clone());
+
+        assert_eq!(provider.get_input(2019, 1).unwrap(), "12\n14\n");
+        assert_eq!(provider.get_input(2019, 1).unwrap(), "12\n14\n");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, INPUT_URL);
+        assert_eq!(requests[0].header("cookie"), Some("session=cafe"));
+    }
+
+    #[test]
+    fn test_error_statuses() {
+        let transport = Arc::new(FakeTransport::new());
+        let provider = fake_provider("error_statuses", transport.clone());
+
+        transport.push_response(INPUT_URL, HttpResponse::new(400, "Please log in"));
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::InvalidSession)
+        ));
+
+        transport.push_response(
+            INPUT_URL,
+            HttpResponse::new(
+                404,
+                "Please don't repeatedly request this endpoint before it unlocks!",
+            ),
+        );
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::NotYetUnlocked(_))
+        ));
+
+        transport.push_response(INPUT_URL, HttpResponse::new(404, "Not Found"));
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::HttpStatus(404))
+        ));
+
+        for _ in 0..3 {
+            transport.push_response(INPUT_URL, HttpResponse::new(503, "Unavailable"));
+        }
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::HttpStatus(503))
+        ));
+
+        transport.push_response(INPUT_URL, HttpResponse::new(200, "<!DOCTYPE html>"));
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::UnexpectedContent)
+        ));
+
+        assert!(!provider.cache().dir().join("2019_1.txt").exists());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(INPUT_URL, HttpResponse::new(200, "12\n"));
+        transport.push_response(INPUT_URL, HttpResponse::new(200, "14\n"));
+        let provider = fake_provider("refresh", transport.clone());
+
+        assert_eq!(provider.get_input(2019, 1).unwrap(), "12\n");
+        let provider = provider.with_refresh(true);
+        assert_eq!(provider.get_input(2019, 1).unwrap(), "14\n");
+        assert_eq!(transport.requests().len(), 2);
+
+        assert!(provider.invalidate(2019, 1).unwrap());
+        assert!(!provider.invalidate(2019, 1).unwrap());
+    }
+
+    #[test]
+    fn test_puzzle_page() {
+        let transport = Arc::new(FakeTransport::new());
+        let html = include_str!("aoc_input/puzzle_example.html");
+        transport.push_response("http://fake/2019/day/2", HttpResponse::new(200, html));
+        let provider = fake_provider("puzzle_page", transport.clone());
+
+        let page = provider.get_puzzle_page(2019, 2).unwrap();
+        assert_eq!(page.part(1).unwrap().answers, ["3500"]);
+        assert_eq!(provider.get_puzzle_page(2019, 2).unwrap(), page);
+        assert_eq!(transport.requests().len(), 1);
+        assert!(provider.cache().dir().join("2019_2.html").exists());
+    }
+
+    #[test]
+    fn test_retries_and_user_agent() {
+        let server = StubServer::serve(vec![
+            HttpResponse::new(503, "Unavailable"),
+            HttpResponse::new(502, "Bad Gateway"),
+            HttpResponse::new(200, "R8,U5,L5,D3\n"),
+        ]);
+        let provider = test_provider("retries", &server.base_url).with_user_agent("aoc-tests");
+
+        assert_eq!(provider.get_input(2019, 3).unwrap(), "R8,U5,L5,D3\n");
+
+        let requests = server.finish();
+        assert_eq!(requests.len(), 3);
+        assert!(requests
+            .iter()
+            .all(|r| r.header("user-agent") == Some("aoc-tests")));
+    }
+
+    #[test]
+    fn test_submit() {
+        let server = StubServer::serve(vec![
+            HttpResponse::new(
+                200,
+                "<article><p>That's not the right answer; your answer is too high.</p></article>",
+            ),
+            HttpResponse::new(200, "<article><p>That's the right answer!</p></article>"),
+        ]);
+        let provider = test_provider("submit", &server.base_url);
+
+        assert_eq!(
+            provider.submit(2019, 1, 1, 5000).unwrap(),
+            SubmitOutcome::Submitted(Verdict::TooHigh)
+        );
+        assert_eq!(
+            provider.submit(2019, 1, 1, 6000).unwrap(),
+            SubmitOutcome::Rejected(submit::LocalRejection::AtOrAboveTooHigh("5000".to_string()))
+        );
+        assert_eq!(
+            provider.submit(2019, 1, 1, 3318).unwrap(),
+            SubmitOutcome::Submitted(Verdict::Correct)
+        );
+        assert_eq!(
+            provider.submit(2019, 1, 1, 3319).unwrap(),
+            SubmitOutcome::Rejected(submit::LocalRejection::AlreadySolved("3318".to_string()))
+        );
+        assert!(matches!(
+            provider.submit(2019, 1, 3, 1),
+            Err(InputError::InvalidPart(3))
+        ));
+        assert!(matches!(
+            provider.submit(2019, 1, 1, "33\t18"),
+            Err(InputError::InvalidAnswer(_))
+        ));
+
+        let requests = server.finish();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].request_line, "POST /2019/day/1/answer HTTP/1.1");
+        assert_eq!(requests[0].body, "level=1&answer=5000");
+        assert_eq!(requests[1].body, "level=1&answer=3318");
+        assert_eq!(provider.ledger(2019, 1).unwrap().attempts().len(), 2);
+    }
+
+    #[test]
+    fn test_calendar_checks() {
+        let transport = Arc::new(FakeTransport::new());
+        let provider = fake_provider("calendar_checks", transport.clone());
+
+        assert!(matches!(
+            provider.get_input(2014, 1),
+            Err(InputError::InvalidPuzzle(2014, 0))
+        ));
+        assert!(matches!(
+            provider.get_input(2019, 26),
+            Err(InputError::InvalidPuzzle(2019, 26))
+        ));
+        match provider.get_input(2099, 1) {
+            Err(InputError::NotYetUnlocked(remaining)) => assert!(!remaining.is_zero()),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            provider.submit(2099, 1, 1, 42),
+            Err(InputError::NotYetUnlocked(_))
+        ));
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn test_offline() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(INPUT_URL, HttpResponse::new(200, "12\n"));
+        let provider = fake_provider("offline", transport.clone());
+
+        let offline = provider.clone().with_offline(true);
+        assert!(matches!(
+            offline.get_input(2019, 1),
+            Err(InputError::Offline)
+        ));
+        assert!(transport.requests().is_empty());
+
+        assert_eq!(provider.get_input(2019, 1).unwrap(), "12\n");
+        let offline = offline.with_refresh(true);
+        assert_eq!(offline.get_input(2019, 1).unwrap(), "12\n");
+        assert!(matches!(
+            offline.submit(2019, 1, 1, 3318),
+            Err(InputError::Offline)
+        ));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_missing_session() {
+        let provider = InputProvider::new()
+            .with_session(SessionSource::Env("AOC_TEST_UNSET_SESSION".to_string()))
+            .with_cache_dir(scratch_dir("missing_session"))
+            .with_transport(Arc::new(FakeTransport::new()));
+        assert!(matches!(
+            provider.get_input(2019, 1),
+            Err(InputError::MissingSession)
+        ));
+    }
+
+    #[test]
+    fn test_config_file_session() {
+        let dir = scratch_dir("config_file_session");
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config");
+        std::fs::write(&config, "# aoc\nyear = 2019\nsession = \"beef\"\n").unwrap();
+
+        let provider = InputProvider::new().with_session(SessionSource::ConfigFile(config));
+        assert_eq!(provider.session_key().unwrap(), "beef");
+    }
+
+    #[test]
+    fn test_stub_server() {
+        let server = StubServer::serve(vec![
+            HttpResponse::new(200, "1,0,0,3,99\n"),
+            HttpResponse::new(500, "Oops"),
+        ]);
+        let provider =
+            test_provider("stub_server", &server.base_url).with_retry_policy(RetryPolicy::none());
+
+        assert_eq!(provider.get_input(2019, 2).unwrap(), "1,0,0,3,99\n");
+        assert!(matches!(
+            provider.get_input(2019, 5),
+            Err(InputError::HttpStatus(500))
+        ));
+
+        let requests = server.finish();
+        assert_eq!(requests[0].request_line, "GET /2019/day/2/input HTTP/1.1");
+        assert_eq!(requests[0].header("cookie"), Some("session=cafe"));
+        assert_eq!(requests[1].request_line, "GET /2019/day/5/input HTTP/1.1");
+    }
+}
@@ -0,0 +1,352 @@
+use super::transport::HttpResponse;
+use super::{InputError, InputResult};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_DIR: &str = "input_cache";
+// From target/<profile>/ up to the checkout, so every build profile shares it
+const EXE_RELATIVE_CACHE_DIR: &[&str] = &["..", "..", CACHE_DIR];
+const CACHE_DIR_ENV_VAR: &str = "AOC_CACHE_DIR";
+const XDG_CACHE_SUBDIR: &str = "aoc";
+const METADATA_EXTENSION: &str = "meta";
+
+// Resolved at run time: the environment first, then next to the executable, then
+// the working directory if the executable's location is unknown
+pub fn default_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV_VAR) {
+        return PathBuf::from(dir);
+    }
+
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        let mut path = PathBuf::from(dir);
+        path.push(XDG_CACHE_SUBDIR);
+        return path;
+    }
+
+    match std::env::current_exe() {
+        Ok(mut path) => {
+            path.pop();
+            path.push(EXE_RELATIVE_CACHE_DIR.iter().collect::<PathBuf>());
+            path
+        }
+        Err(_) => PathBuf::from(CACHE_DIR),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheMetadata {
+    pub status: u16,
+    pub fetched_at: u64,
+    pub content_hash: String,
+}
+
+impl CacheMetadata {
+    fn to_file_string(&self) -> String {
+        format!(
+            "status={}\nfetched_at={}\nsha256={}\n",
+            self.status, self.fetched_at, self.content_hash
+        )
+    }
+
+    fn from_file_string(s: &str) -> Option<Self> {
+        let mut status = None;
+        let mut fetched_at = None;
+        let mut content_hash = None;
+
+        for line in s.lines() {
+            let (key, value) = line.split_once('=')?;
+            match key {
+                "status" => status = Some(value.parse().ok()?),
+                "fetched_at" => fetched_at = Some(value.parse().ok()?),
+                "sha256" => content_hash = Some(value.to_string()),
+                _ => (),
+            }
+        }
+
+        Some(CacheMetadata {
+            status: status?,
+            fetched_at: fetched_at?,
+            content_hash: content_hash?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryState {
+    Missing,
+    Valid(CacheMetadata),
+    MissingMetadata,
+    BadStatus(u16),
+    HashMismatch,
+}
+
+impl EntryState {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, EntryState::Valid(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub name: String,
+    pub state: EntryState,
+}
+
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn remove_if_exists(path: &Path) -> InputResult<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn metadata_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, METADATA_EXTENSION))
+    }
+
+    pub fn metadata(&self, name: &str) -> InputResult<Option<CacheMetadata>> {
+        match std::fs::read_to_string(self.metadata_path(name)) {
+            Ok(s) => Ok(CacheMetadata::from_file_string(&s)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn read_entry(&self, name: &str) -> InputResult<(EntryState, Option<String>)> {
+        let content = match std::fs::read_to_string(self.entry_path(name)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((EntryState::Missing, None))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let state = match self.metadata(name)? {
+            None => EntryState::MissingMetadata,
+            Some(meta) if meta.status != 200 => EntryState::BadStatus(meta.status),
+            Some(meta) if meta.content_hash != content_hash(&content) => EntryState::HashMismatch,
+            Some(meta) => EntryState::Valid(meta),
+        };
+        Ok((state, Some(content)))
+    }
+
+    pub fn validate(&self, name: &str) -> InputResult<EntryState> {
+        Ok(self.read_entry(name)?.0)
+    }
+
+    pub fn load(&self, name: &str) -> InputResult<Option<String>> {
+        match self.read_entry(name)? {
+            (EntryState::Valid(_), content) => Ok(content),
+            (EntryState::Missing, _) => Ok(None),
+            _ => {
+                self.invalidate(name)?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn store(&self, name: &str, response: &HttpResponse) -> InputResult<CacheMetadata> {
+        if response.status != 200 {
+            return Err(InputError::HttpStatus(response.status));
+        }
+
+        let meta = CacheMetadata {
+            status: response.status,
+            fetched_at: unix_now(),
+            content_hash: content_hash(&response.body),
+        };
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.entry_path(name), &response.body)?;
+        std::fs::write(self.metadata_path(name), meta.to_file_string())?;
+        Ok(meta)
+    }
+
+    pub fn invalidate(&self, name: &str) -> InputResult<bool> {
+        let removed_entry = remove_if_exists(&self.entry_path(name))?;
+        let removed_metadata = remove_if_exists(&self.metadata_path(name))?;
+        Ok(removed_entry || removed_metadata)
+    }
+
+    fn entry_names(&self) -> InputResult<Vec<String>> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let meta_suffix = format!(".{}", METADATA_EXTENSION);
+        let mut names = Vec::new();
+        for dir_entry in dir {
+            let dir_entry = dir_entry?;
+            if !dir_entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let file_name = dir_entry.file_name().to_string_lossy().into_owned();
+            let name = match file_name.strip_suffix(&meta_suffix) {
+                Some(name) => name.to_string(),
+                None => file_name,
+            };
+            names.push(name);
+        }
+
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    pub fn list(&self) -> InputResult<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for name in self.entry_names()? {
+            let state = self.validate(&name)?;
+            entries.push(CacheEntry { name, state });
+        }
+        Ok(entries)
+    }
+
+    pub fn prune(&self, max_age: Option<Duration>) -> InputResult<Vec<String>> {
+        let now = unix_now();
+        let mut pruned = Vec::new();
+
+        for entry in self.list()? {
+            let expired = match (&entry.state, max_age) {
+                (EntryState::Valid(meta), Some(max_age)) => {
+                    now.saturating_sub(meta.fetched_at) > max_age.as_secs()
+                }
+                (EntryState::Valid(_), None) => false,
+                _ => true,
+            };
+
+            if expired {
+                self.invalidate(&entry.name)?;
+                pruned.push(entry.name);
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::scratch_dir;
+    use super::*;
+
+    #[test]
+    fn test_store_and_load() {
+        let cache = Cache::new(scratch_dir("cache_store_and_load"));
+        assert_eq!(cache.load("2019_1.txt").unwrap(), None);
+
+        let meta = cache
+            .store("2019_1.txt", &HttpResponse::new(200, "12\n"))
+            .unwrap();
+        assert_eq!(meta.status, 200);
+        assert_eq!(meta.content_hash, content_hash("12\n"));
+        assert_eq!(
+            cache.validate("2019_1.txt").unwrap(),
+            EntryState::Valid(meta)
+        );
+        assert_eq!(cache.load("2019_1.txt").unwrap().unwrap(), "12\n");
+    }
+
+    #[test]
+    fn test_rejects_error_responses() {
+        let cache = Cache::new(scratch_dir("cache_rejects_errors"));
+        let response = HttpResponse::new(404, "Please don't repeatedly request this endpoint");
+        assert!(matches!(
+            cache.store("2019_1.txt", &response),
+            Err(InputError::HttpStatus(404))
+        ));
+        assert_eq!(cache.validate("2019_1.txt").unwrap(), EntryState::Missing);
+    }
+
+    #[test]
+    fn test_evicts_bad_entries() {
+        let cache = Cache::new(scratch_dir("cache_evicts_bad"));
+        cache
+            .store("2019_1.txt", &HttpResponse::new(200, "12\n"))
+            .unwrap();
+        std::fs::write(cache.dir().join("2019_1.txt"), "<html>").unwrap();
+        assert_eq!(
+            cache.validate("2019_1.txt").unwrap(),
+            EntryState::HashMismatch
+        );
+        assert_eq!(cache.load("2019_1.txt").unwrap(), None);
+        assert_eq!(cache.validate("2019_1.txt").unwrap(), EntryState::Missing);
+
+        std::fs::write(cache.dir().join("2019_2.txt"), "1,0,0,0,99").unwrap();
+        assert_eq!(
+            cache.validate("2019_2.txt").unwrap(),
+            EntryState::MissingMetadata
+        );
+        assert!(cache.invalidate("2019_2.txt").unwrap());
+        assert!(!cache.invalidate("2019_2.txt").unwrap());
+    }
+
+    #[test]
+    fn test_list_and_prune() {
+        let cache = Cache::new(scratch_dir("cache_list_and_prune"));
+        assert!(cache.list().unwrap().is_empty());
+
+        cache
+            .store("2019_1.txt", &HttpResponse::new(200, "12\n"))
+            .unwrap();
+        cache
+            .store("2019_2.txt", &HttpResponse::new(200, "1,0,0,0,99"))
+            .unwrap();
+        std::fs::write(cache.dir().join("2019_3.txt"), "R8,U5").unwrap();
+        std::fs::write(cache.dir().join("2019_4.txt.meta"), "status=404").unwrap();
+
+        let entries = cache.list().unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["2019_1.txt", "2019_2.txt", "2019_3.txt", "2019_4.txt"]
+        );
+        assert!(entries[0].state.is_valid());
+        assert_eq!(entries[2].state, EntryState::MissingMetadata);
+        assert_eq!(entries[3].state, EntryState::Missing);
+
+        assert_eq!(cache.prune(None).unwrap(), ["2019_3.txt", "2019_4.txt"]);
+        assert_eq!(cache.list().unwrap().len(), 2);
+
+        let stale = CacheMetadata {
+            fetched_at: 0,
+            ..cache.metadata("2019_1.txt").unwrap().unwrap()
+        };
+        std::fs::write(cache.metadata_path("2019_1.txt"), stale.to_file_string()).unwrap();
+        let max_age = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(cache.prune(Some(max_age)).unwrap(), ["2019_1.txt"]);
+        assert_eq!(cache.list().unwrap().len(), 1);
+    }
+}
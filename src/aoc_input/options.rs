@@ -0,0 +1,207 @@
+use super::{InputError, InputProvider, InputResult};
+use lazy_static::lazy_static;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const INPUT_ARG: &str = "--input";
+const REFRESH_ARG: &str = "--refresh";
+const OFFLINE_ARG: &str = "--offline";
+const INPUT_ENV_VAR: &str = "AOC_INPUT";
+const OFFLINE_ENV_VAR: &str = "AOC_OFFLINE";
+const STDIN_PATH: &str = "-";
+const OPTION_PREFIX: &str = "--";
+
+lazy_static! {
+    // Stdin can only be read once, so the first override read is kept
+    static ref OVERRIDE: Mutex<Option<(InputSource, String)>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    Puzzle,
+    File(PathBuf),
+    Stdin,
+}
+
+impl InputSource {
+    fn from_path(path: &str) -> Self {
+        if path == STDIN_PATH {
+            InputSource::Stdin
+        } else {
+            InputSource::File(PathBuf::from(path))
+        }
+    }
+
+    pub fn read_override(&self) -> InputResult<Option<String>> {
+        match self {
+            InputSource::Puzzle => Ok(None),
+            InputSource::File(path) => std::fs::read_to_string(path)
+                .map(Some)
+                .map_err(InputError::InputOverride),
+            InputSource::Stdin => {
+                let mut input = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut input)
+                    .map_err(InputError::InputOverride)?;
+                Ok(Some(input))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputOptions {
+    pub source: InputSource,
+    pub refresh: bool,
+    pub offline: bool,
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(value.trim(), "" | "0" | "false" | "no")
+}
+
+impl InputOptions {
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> InputResult<Self> {
+        let mut options = InputOptions {
+            source: env(INPUT_ENV_VAR)
+                .map(|path| InputSource::from_path(&path))
+                .unwrap_or(InputSource::Puzzle),
+            refresh: false,
+            offline: env(OFFLINE_ENV_VAR).is_some_and(|v| is_truthy(&v)),
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(path) = arg.strip_prefix("--input=") {
+                options.source = InputSource::from_path(path);
+                continue;
+            }
+
+            match arg.as_str() {
+                INPUT_ARG => {
+                    let path = args
+                        .next()
+                        .ok_or(InputError::InvalidOptions("Missing path after --input"))?;
+                    options.source = InputSource::from_path(&path);
+                }
+                REFRESH_ARG => options.refresh = true,
+                OFFLINE_ARG => options.offline = true,
+                // Programs take their own arguments as plain words
+                _ if arg.starts_with(OPTION_PREFIX) => {
+                    return Err(InputError::InvalidOptions("Unknown option"));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn from_env() -> InputResult<Self> {
+        Self::parse(std::env::args().skip(1), |var| std::env::var(var).ok())
+    }
+
+    pub fn read_override(&self) -> InputResult<Option<String>> {
+        let mut cached = OVERRIDE.lock().unwrap();
+        if let Some((source, input)) = cached.as_ref() {
+            if *source == self.source {
+                return Ok(Some(input.clone()));
+            }
+        }
+
+        let input = self.source.read_override()?;
+        if let Some(input) = &input {
+            *cached = Some((self.source.clone(), input.clone()));
+        }
+        Ok(input)
+    }
+
+    pub fn apply(&self, provider: InputProvider) -> InputProvider {
+        provider
+            .with_refresh(self.refresh)
+            .with_offline(self.offline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::scratch_dir;
+    use super::*;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = InputOptions::parse(args(&[]), no_env).unwrap();
+        assert_eq!(options.source, InputSource::Puzzle);
+        assert!(!options.refresh && !options.offline);
+
+        let options = args(&["--input", "ex.txt", "--refresh"]);
+        let options = InputOptions::parse(options, no_env).unwrap();
+        assert_eq!(options.source, InputSource::File(PathBuf::from("ex.txt")));
+        assert!(options.refresh);
+
+        let options = InputOptions::parse(args(&["--input=-", "--offline"]), no_env).unwrap();
+        assert_eq!(options.source, InputSource::Stdin);
+        assert!(options.offline);
+
+        assert!(matches!(
+            InputOptions::parse(args(&["--input"]), no_env),
+            Err(InputError::InvalidOptions(_))
+        ));
+        for typo in ["--ofline", "--inputs", "--input-file=ex.txt"] {
+            assert!(matches!(
+                InputOptions::parse(args(&[typo]), no_env),
+                Err(InputError::InvalidOptions(_))
+            ));
+        }
+        assert!(InputOptions::parse(args(&["play", "-"]), no_env).is_ok());
+    }
+
+    #[test]
+    fn test_override_read_once() {
+        let dir = scratch_dir("override");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input.txt");
+        std::fs::write(&path, "12\n").unwrap();
+        let options = InputOptions {
+            source: InputSource::File(path.clone()),
+            refresh: false,
+            offline: false,
+        };
+        assert_eq!(options.read_override().unwrap().unwrap(), "12\n");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(options.read_override().unwrap().unwrap(), "12\n");
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let env = |var: &str| match var {
+            "AOC_INPUT" => Some("theirs.txt".to_string()),
+            "AOC_OFFLINE" => Some("1".to_string()),
+            _ => None,
+        };
+        let options = InputOptions::parse(args(&[]), env).unwrap();
+        assert_eq!(
+            options.source,
+            InputSource::File(PathBuf::from("theirs.txt"))
+        );
+        assert!(options.offline);
+
+        let options = InputOptions::parse(args(&["--input", "mine.txt"]), env).unwrap();
+        assert_eq!(options.source, InputSource::File(PathBuf::from("mine.txt")));
+
+        let options = InputOptions::parse(args(&[]), |_| Some("0".to_string())).unwrap();
+        assert!(!options.offline);
+    }
+}
@@ -0,0 +1,106 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref ARTICLE_RE: Regex =
+        Regex::new(r#"(?s)<article class="day-desc">(.*?)</article>"#).unwrap();
+    static ref PRE_CODE_RE: Regex = Regex::new(r"(?s)<pre><code>(.*?)</code></pre>").unwrap();
+    static ref ANSWER_RE: Regex = Regex::new(r"(?s)<code><em>(.*?)</em></code>").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PuzzlePart {
+    pub examples: Vec<String>,
+    pub answers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PuzzlePage {
+    pub parts: Vec<PuzzlePart>,
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn strip_tags(s: &str) -> String {
+    decode_entities(&TAG_RE.replace_all(s, ""))
+}
+
+fn parse_part(article: &str) -> PuzzlePart {
+    let examples = PRE_CODE_RE
+        .captures_iter(article)
+        .map(|c| strip_tags(&c[1]))
+        .collect();
+
+    let prose = PRE_CODE_RE.replace_all(article, "");
+    let answers = ANSWER_RE
+        .captures_iter(&prose)
+        .map(|c| strip_tags(&c[1]))
+        .collect();
+
+    PuzzlePart { examples, answers }
+}
+
+impl PuzzlePage {
+    pub fn parse(html: &str) -> Self {
+        let parts = ARTICLE_RE
+            .captures_iter(html)
+            .map(|c| parse_part(&c[1]))
+            .collect();
+        PuzzlePage { parts }
+    }
+
+    pub fn part(&self, part: u8) -> Option<&PuzzlePart> {
+        self.parts.get((part as usize).checked_sub(1)?)
+    }
+
+    pub fn examples(&self) -> impl Iterator<Item = &str> {
+        self.parts
+            .iter()
+            .flat_map(|p| p.examples.iter().map(String::as_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fixture() {
+        let page = PuzzlePage::parse(include_str!("puzzle_example.html"));
+        assert_eq!(page.parts.len(), 2);
+
+        let part1 = page.part(1).unwrap();
+        assert_eq!(
+            part1.examples,
+            [
+                "1,9,10,3,2,3,11,0,99,30,40,50",
+                "1,0,0,0,99 becomes 2,0,0,0,99 (1 + 1 = 2).\n2,3,0,3,99 becomes 2,3,0,6,99 (3 * 2 = 6).\n",
+            ]
+        );
+        assert_eq!(part1.answers, ["3500"]);
+
+        let part2 = page.part(2).unwrap();
+        assert_eq!(part2.examples, ["noun & verb > 0\n"]);
+        assert_eq!(part2.answers, ["1202"]);
+
+        assert!(page.part(0).is_none());
+        assert!(page.part(3).is_none());
+        assert_eq!(page.examples().count(), 3);
+    }
+
+    #[test]
+    fn test_parse_locked_part2() {
+        let html = r#"<article class="day-desc"><pre><code>abc</code></pre></article>"#;
+        let page = PuzzlePage::parse(html);
+        assert_eq!(page.parts.len(), 1);
+        assert!(page.part(1).unwrap().answers.is_empty());
+        assert!(page.part(2).is_none());
+    }
+}
@@ -0,0 +1,417 @@
+use super::{InputError, InputResult};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+lazy_static! {
+    static ref ARTICLE_RE: Regex = Regex::new(r"(?s)<article>(.*?)</article>").unwrap();
+    static ref WAIT_RE: Regex = Regex::new(r"You have (?:(\d+)m )?(\d+)s left to wait").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    TooHigh,
+    TooLow,
+    Incorrect,
+    RateLimited(Duration),
+    WrongLevel,
+    Unknown,
+}
+
+impl Verdict {
+    pub fn parse(html: &str) -> Self {
+        let text = match ARTICLE_RE.captures(html) {
+            Some(c) => TAG_RE.replace_all(&c[1], "").into_owned(),
+            None => return Verdict::Unknown,
+        };
+
+        if text.contains("That's the right answer") {
+            Verdict::Correct
+        } else if text.contains("your answer is too high") {
+            Verdict::TooHigh
+        } else if text.contains("your answer is too low") {
+            Verdict::TooLow
+        } else if text.contains("That's not the right answer") {
+            Verdict::Incorrect
+        } else if let Some(c) = WAIT_RE.captures(&text) {
+            let minutes: u64 = c.get(1).map_or(0, |m| m.as_str().parse().unwrap());
+            let seconds: u64 = c[2].parse().unwrap();
+            Verdict::RateLimited(Duration::from_secs(minutes * 60 + seconds))
+        } else if text.contains("You don't seem to be solving the right level") {
+            Verdict::WrongLevel
+        } else {
+            Verdict::Unknown
+        }
+    }
+
+    fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Verdict::Correct | Verdict::TooHigh | Verdict::TooLow | Verdict::Incorrect
+        )
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::TooHigh => write!(f, "too_high"),
+            Verdict::TooLow => write!(f, "too_low"),
+            Verdict::Incorrect => write!(f, "incorrect"),
+            Verdict::RateLimited(wait) => write!(f, "wait:{}", wait.as_secs()),
+            Verdict::WrongLevel => write!(f, "wrong_level"),
+            Verdict::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(secs) = s.strip_prefix("wait:") {
+            let secs = secs.parse().map_err(|_| "Invalid wait duration")?;
+            return Ok(Verdict::RateLimited(Duration::from_secs(secs)));
+        }
+
+        match s {
+            "correct" => Ok(Verdict::Correct),
+            "too_high" => Ok(Verdict::TooHigh),
+            "too_low" => Ok(Verdict::TooLow),
+            "incorrect" => Ok(Verdict::Incorrect),
+            "wrong_level" => Ok(Verdict::WrongLevel),
+            "unknown" => Ok(Verdict::Unknown),
+            _ => Err("Invalid verdict"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalRejection {
+    AlreadySolved(String),
+    AlreadySubmitted(Verdict),
+    AtOrAboveTooHigh(String),
+    AtOrBelowTooLow(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmitOutcome {
+    Submitted(Verdict),
+    Rejected(LocalRejection),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    pub part: u8,
+    pub submitted_at: u64,
+    pub verdict: Verdict,
+    pub answer: String,
+}
+
+impl fmt::Display for Attempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.part, self.submitted_at, self.verdict, self.answer
+        )
+    }
+}
+
+impl FromStr for Attempt {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut split = s.splitn(4, '\t');
+        let mut next = || split.next().ok_or("Missing ledger field");
+
+        Ok(Attempt {
+            part: next()?.parse().map_err(|_| "Invalid part")?,
+            submitted_at: next()?.parse().map_err(|_| "Invalid timestamp")?,
+            verdict: next()?.parse()?,
+            answer: next()?.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    path: PathBuf,
+    attempts: Vec<Attempt>,
+}
+
+impl Ledger {
+    pub fn load(path: impl Into<PathBuf>) -> InputResult<Self> {
+        let path = path.into();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // A line that does not parse is an error rather than skipped, so that it
+        // is never lost when the ledger is next written
+        let attempts = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                line.parse()
+                    .map_err(|_| InputError::CorruptLedger(path.clone(), i + 1))
+            })
+            .collect::<InputResult<_>>()?;
+        Ok(Ledger { path, attempts })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn attempts(&self) -> &[Attempt] {
+        &self.attempts
+    }
+
+    fn part_attempts(&self, part: u8) -> impl Iterator<Item = &Attempt> {
+        self.attempts.iter().filter(move |a| a.part == part)
+    }
+
+    fn bound(&self, part: u8, verdict: Verdict) -> Option<(i128, &str)> {
+        self.part_attempts(part)
+            .filter(|a| a.verdict == verdict)
+            .filter_map(|a| Some((a.answer.parse::<i128>().ok()?, a.answer.as_str())))
+            .reduce(|best, cur| match verdict {
+                Verdict::TooHigh if cur.0 < best.0 => cur,
+                Verdict::TooLow if cur.0 > best.0 => cur,
+                _ => best,
+            })
+    }
+
+    pub fn check(&self, part: u8, answer: &str) -> Option<LocalRejection> {
+        if let Some(solved) = self
+            .part_attempts(part)
+            .find(|a| a.verdict == Verdict::Correct)
+        {
+            return Some(LocalRejection::AlreadySolved(solved.answer.clone()));
+        }
+
+        if let Some(previous) = self
+            .part_attempts(part)
+            .find(|a| a.answer == answer && a.verdict.is_final())
+        {
+            return Some(LocalRejection::AlreadySubmitted(previous.verdict.clone()));
+        }
+
+        let value = answer.parse::<i128>().ok()?;
+        match self.bound(part, Verdict::TooHigh) {
+            Some((high, s)) if value >= high => {
+                return Some(LocalRejection::AtOrAboveTooHigh(s.to_string()))
+            }
+            _ => (),
+        }
+        match self.bound(part, Verdict::TooLow) {
+            Some((low, s)) if value <= low => {
+                return Some(LocalRejection::AtOrBelowTooLow(s.to_string()))
+            }
+            _ => (),
+        }
+        None
+    }
+
+    pub fn record(&mut self, part: u8, answer: &str, verdict: Verdict) -> InputResult<()> {
+        let answer = normalize_answer(answer)?;
+        let submitted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let attempt = Attempt {
+            part,
+            submitted_at,
+            verdict,
+            answer: answer.to_string(),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", attempt)?;
+        self.attempts.push(attempt);
+        Ok(())
+    }
+}
+
+// Ledger lines are tab separated, so answers must stay on one field of one line
+pub fn normalize_answer(answer: &str) -> InputResult<&str> {
+    let trimmed = answer.trim();
+    if trimmed.is_empty() || trimmed.chars().any(char::is_control) {
+        return Err(InputError::InvalidAnswer(answer.to_string()));
+    }
+    Ok(trimmed)
+}
+
+pub fn encode_form_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::scratch_dir;
+    use super::*;
+
+    fn article(text: &str) -> String {
+        format!("<main>\n<article><p>{}</p></article>\n</main>", text)
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        let correct = article("That's the right answer!  You are <span class=\"day-success\">one gold star</span> closer.");
+        assert_eq!(Verdict::parse(&correct), Verdict::Correct);
+
+        let high = article(
+            "That's not the right answer; your answer is too high.  Please wait one minute.",
+        );
+        assert_eq!(Verdict::parse(&high), Verdict::TooHigh);
+
+        let low = article("That's not the right answer; your answer is too low.");
+        assert_eq!(Verdict::parse(&low), Verdict::TooLow);
+
+        let wrong = article("That's not the right answer.  If you're stuck, make sure you're using the full input data.");
+        assert_eq!(Verdict::parse(&wrong), Verdict::Incorrect);
+
+        let wait = article("You gave an answer too recently.  You have 1m 4s left to wait.");
+        assert_eq!(
+            Verdict::parse(&wait),
+            Verdict::RateLimited(Duration::from_secs(64))
+        );
+
+        let wait = article("You gave an answer too recently.  You have 37s left to wait.");
+        assert_eq!(
+            Verdict::parse(&wait),
+            Verdict::RateLimited(Duration::from_secs(37))
+        );
+
+        let level =
+            article("You don't seem to be solving the right level.  Did you already complete it?");
+        assert_eq!(Verdict::parse(&level), Verdict::WrongLevel);
+
+        assert_eq!(Verdict::parse("<html></html>"), Verdict::Unknown);
+    }
+
+    #[test]
+    fn test_ledger() {
+        let path = scratch_dir("ledger").join("2019_1.txt");
+        let mut ledger = Ledger::load(&path).unwrap();
+        assert_eq!(ledger.check(1, "100"), None);
+
+        ledger.record(1, "100", Verdict::TooHigh).unwrap();
+        ledger.record(1, "50", Verdict::TooHigh).unwrap();
+        ledger.record(1, "10", Verdict::TooLow).unwrap();
+        ledger
+            .record(1, "20", Verdict::RateLimited(Duration::from_secs(30)))
+            .unwrap();
+        ledger.record(1, "30", Verdict::Incorrect).unwrap();
+
+        let ledger = Ledger::load(&path).unwrap();
+        assert_eq!(ledger.attempts().len(), 5);
+        assert_eq!(
+            ledger.check(1, "30"),
+            Some(LocalRejection::AlreadySubmitted(Verdict::Incorrect))
+        );
+        assert_eq!(
+            ledger.check(1, "75"),
+            Some(LocalRejection::AtOrAboveTooHigh("50".to_string()))
+        );
+        assert_eq!(
+            ledger.check(1, "10"),
+            Some(LocalRejection::AlreadySubmitted(Verdict::TooLow))
+        );
+        assert_eq!(
+            ledger.check(1, "-3"),
+            Some(LocalRejection::AtOrBelowTooLow("10".to_string()))
+        );
+        assert_eq!(ledger.check(1, "20"), None);
+        assert_eq!(ledger.check(1, "abc"), None);
+        assert_eq!(ledger.check(2, "75"), None);
+    }
+
+    #[test]
+    fn test_ledger_solved() {
+        let mut ledger = Ledger::load(scratch_dir("ledger_solved").join("2019_2.txt")).unwrap();
+        ledger.record(2, "1202", Verdict::Correct).unwrap();
+        assert_eq!(
+            ledger.check(2, "1203"),
+            Some(LocalRejection::AlreadySolved("1202".to_string()))
+        );
+        assert_eq!(ledger.check(1, "1203"), None);
+    }
+
+    #[test]
+    fn test_ledger_answers() {
+        let path = scratch_dir("ledger_answers").join("2019_3.txt");
+        let mut ledger = Ledger::load(&path).unwrap();
+        ledger.record(1, " 12 34\n", Verdict::Incorrect).unwrap();
+        assert!(matches!(
+            ledger.record(1, "12\t34", Verdict::Incorrect),
+            Err(InputError::InvalidAnswer(_))
+        ));
+        assert!(matches!(
+            ledger.record(1, "1\n2", Verdict::Incorrect),
+            Err(InputError::InvalidAnswer(_))
+        ));
+        assert!(matches!(
+            ledger.record(1, " \n", Verdict::Incorrect),
+            Err(InputError::InvalidAnswer(_))
+        ));
+
+        let loaded = Ledger::load(&path).unwrap();
+        assert_eq!(loaded.attempts(), ledger.attempts());
+        assert_eq!(loaded.attempts()[0].answer, "12 34");
+        let attempt = &loaded.attempts()[0];
+        assert_eq!(attempt.to_string().parse::<Attempt>().unwrap(), *attempt);
+    }
+
+    #[test]
+    fn test_corrupt_ledger() {
+        let path = scratch_dir("ledger_corrupt").join("2019_4.txt");
+        let mut ledger = Ledger::load(&path).unwrap();
+        ledger.record(1, "12", Verdict::TooLow).unwrap();
+
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        content.push_str("1\tgarbage\n");
+        std::fs::write(&path, &content).unwrap();
+        assert!(matches!(
+            Ledger::load(&path),
+            Err(InputError::CorruptLedger(_, 2))
+        ));
+
+        // Recording appends, leaving earlier lines as they were
+        ledger.record(1, "13", Verdict::TooLow).unwrap();
+        let after = std::fs::read_to_string(&path).unwrap();
+        assert!(after.starts_with(&content));
+    }
+
+    #[test]
+    fn test_encode_form_value() {
+        assert_eq!(encode_form_value("12345"), "12345");
+        assert_eq!(encode_form_value("a,b c&d"), "a%2Cb+c%26d");
+    }
+}
@@ -0,0 +1,215 @@
+use super::InputResult;
+use fs2::FileExt;
+use lazy_static::lazy_static;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+fn unix_now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    min_interval: Duration,
+    lock_path: Option<PathBuf>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_INTERVAL)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        RateLimiter {
+            min_interval,
+            lock_path: None,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(Duration::ZERO)
+    }
+
+    pub fn with_lock_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lock_path = Some(path.into());
+        self
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    fn wait_cross_process(&self, path: &PathBuf) -> InputResult<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        file.lock_exclusive()?;
+
+        // Reserve the next slot and release the lock before sleeping until it
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let now = unix_now_millis();
+        let next = match content.trim().parse::<u128>() {
+            Ok(last) => now.max(last + self.min_interval.as_millis()),
+            Err(_) => now,
+        };
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", next)?;
+        FileExt::unlock(&file)?;
+
+        if next > now {
+            std::thread::sleep(Duration::from_millis((next - now) as u64));
+        }
+        Ok(())
+    }
+
+    fn wait_in_process(&self) {
+        let deadline = {
+            let mut last = LAST_REQUEST.lock().unwrap();
+            let now = Instant::now();
+            let deadline = match *last {
+                Some(last) => now.max(last + self.min_interval),
+                None => now,
+            };
+            *last = Some(deadline);
+            deadline
+        };
+        let now = Instant::now();
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        }
+    }
+
+    // The lock file, when there is one, also orders this process's own requests
+    pub fn wait(&self) -> InputResult<()> {
+        if self.min_interval.is_zero() {
+            return Ok(());
+        }
+
+        match &self.lock_path {
+            Some(path) => self.wait_cross_process(path),
+            None => {
+                self.wait_in_process();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_backoff: Duration::ZERO,
+        }
+    }
+
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff * 2u32.saturating_pow(attempt)
+    }
+
+    pub fn should_retry(&self, attempt: u32, status: u16) -> bool {
+        attempt < self.max_retries && (500..600).contains(&status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::scratch_dir;
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let path = scratch_dir("rate_limiter").join(".request.lock");
+        let limiter = RateLimiter::new(Duration::from_millis(50)).with_lock_file(&path);
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.wait().unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let stamp: u128 = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+        assert!(stamp <= unix_now_millis());
+
+        // Waits once per interval, not once for each of the two slots
+        let limiter = RateLimiter::new(Duration::from_millis(200)).with_lock_file(&path);
+        limiter.wait().unwrap();
+        let start = Instant::now();
+        limiter.wait().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert!(start.elapsed() < Duration::from_millis(350));
+    }
+
+    #[test]
+    fn test_rate_limiter_threads() {
+        // Waiting threads queue up behind reserved slots rather than the lock
+        let limiter = RateLimiter::new(Duration::from_millis(50));
+        let start = Instant::now();
+        let threads: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                std::thread::spawn(move || limiter.wait().unwrap())
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(100),
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert!(policy.should_retry(0, 503));
+        assert!(policy.should_retry(1, 500));
+        assert!(!policy.should_retry(2, 503));
+        assert!(!policy.should_retry(0, 404));
+        assert!(!RetryPolicy::none().should_retry(0, 503));
+    }
+}
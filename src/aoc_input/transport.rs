@@ -0,0 +1,141 @@
+use super::InputResult;
+use reqwest::blocking::Client;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        HttpRequest {
+            method: Method::Get,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn post_form(url: impl Into<String>, body: impl Into<String>) -> Self {
+        HttpRequest {
+            method: Method::Post,
+            url: url.into(),
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            )],
+            body: Some(body.into()),
+        }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        HttpResponse {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+pub trait HttpTransport: Debug + Send + Sync {
+    fn send(&self, request: &HttpRequest) -> InputResult<HttpResponse>;
+}
+
+#[derive(Debug, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: &HttpRequest) -> InputResult<HttpResponse> {
+        let mut builder = match request.method {
+            Method::Get => self.client.get(&request.url),
+            Method::Post => self.client.post(&request.url),
+        };
+        for (name, value) in request.headers.iter() {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let resp = builder.send()?;
+        let status = resp.status().as_u16();
+        Ok(HttpResponse::new(status, resp.text()?))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FakeTransport {
+    responses: Mutex<HashMap<String, VecDeque<HttpResponse>>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_response(&self, url: impl Into<String>, response: HttpResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(url.into())
+            .or_default()
+            .push_back(response);
+    }
+
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl HttpTransport for FakeTransport {
+    fn send(&self, request: &HttpRequest) -> InputResult<HttpResponse> {
+        self.requests.lock().unwrap().push(request.clone());
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(&request.url)
+            .and_then(|queue| queue.pop_front());
+        Ok(response.unwrap_or_else(|| HttpResponse::new(404, "Not Found")))
+    }
+}
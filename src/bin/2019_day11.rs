@@ -127,6 +127,7 @@ impl Robot {
                 self.location += self.direction.into();
                 RobotRunResult::Paint(paint_request)
             }
+            status => panic!("Unexpected stop status {:?}", status),
         }
     }
 }
@@ -2,7 +2,7 @@ use aoc::aoc_input::get_input;
 use aoc::intcode::*;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
-use std::ops::{Add, AddAssign, Neg};
+use std::ops::{Add, AddAssign};
 
 #[macro_use]
 extern crate num_derive;
@@ -20,19 +20,6 @@ enum Direction {
     East = 4,
 }
 
-impl Neg for Direction {
-    type Output = Direction;
-
-    fn neg(self) -> Self::Output {
-        match self {
-            Direction::North => Direction::South,
-            Direction::South => Direction::North,
-            Direction::West => Direction::East,
-            Direction::East => Direction::West,
-        }
-    }
-}
-
 #[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 enum DroidReply {
     HitWall = 0,
@@ -146,8 +133,9 @@ impl RepairDroid {
         }
     }
 
-    fn discover_recurse(&mut self, return_direction: Option<Direction>) {
+    fn discover_recurse(&mut self) {
         let current_location = self.droid_location;
+        let snapshot = self.machine.snapshot();
 
         for direction in Direction::iter() {
             let dest = current_location + direction.into();
@@ -156,18 +144,15 @@ impl RepairDroid {
             }
 
             if self.try_move(direction).is_some() {
-                self.discover_recurse(Some(-direction));
-                assert_eq!(self.droid_location, current_location);
+                self.discover_recurse();
+                self.machine.restore(&snapshot);
+                self.droid_location = current_location;
             }
         }
-
-        if let Some(direction) = return_direction {
-            self.try_move(direction).unwrap();
-        }
     }
 
     fn discover(&mut self) {
-        self.discover_recurse(None)
+        self.discover_recurse()
     }
 
     fn bfs_layers(
@@ -76,16 +76,11 @@ impl FromStr for Map {
 }
 
 fn get_initial_map(tape: &Tape) -> Map {
-    let mut machine = IntcodeMachine::new(tape.clone());
-    machine.run_to_completion().unwrap();
-    let output: String = machine
-        .output
-        .borrow_mut()
-        .drain(..)
-        .map(|n| std::char::from_u32(n as u32).unwrap())
-        .collect();
-
-    output.parse().unwrap()
+    let mut ascii = AsciiIntcode::new(tape.clone());
+    let output = ascii.read_until_prompt().unwrap();
+    assert!(output.halted());
+
+    output.text.parse().unwrap()
 }
 
 fn sum_alignment_parameters(map: &Map) -> usize {
@@ -3,13 +3,14 @@ use aoc::intcode::*;
 
 fn main() {
     let input = get_input(2019, 19);
-    let tape = parse_intcode_program(&input);
+    let decoded = DecodedTape::new(parse_intcode_program(&input));
 
     let mut pulled_locations = 0;
 
     for y in 0..50 {
         for x in 0..50 {
-            let mut machine = IntcodeMachine::new(tape.clone());
+            let mut machine: IntcodeMachine =
+                IntcodeMachine::from_decoded(&decoded, new_stream_ref(), new_stream_ref());
             machine.input.borrow_mut().extend(&[x, y]);
             machine.run().unwrap();
             pulled_locations += machine.output.borrow_mut().pop_front().unwrap();
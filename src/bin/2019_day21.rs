@@ -1,5 +1,45 @@
 use aoc::aoc_input::get_input;
+use aoc::intcode::*;
+use aoc::springscript::*;
+
+fn try_script(tape: &Tape, script: &Script) -> Result<isize, String> {
+    let mut droid = AsciiIntcode::new(tape.clone());
+    let prompt = droid.read_until_prompt().unwrap();
+    assert_eq!(prompt.text, "Input instructions:\n");
+
+    droid.send_text(&script.to_string());
+    let report = droid.read_until_prompt().unwrap();
+    assert!(report.halted());
+    report.answer.ok_or(report.text)
+}
+
+// Grows the set of hulls the script must cross from the droid's failure
+// reports until a synthesized script makes it all the way
+fn survey_hull_damage(tape: &Tape, mode: Mode) -> isize {
+    let mut hulls = Vec::<Hull>::new();
+    loop {
+        let script = synthesize(mode, &hulls).expect("No script crosses every hull");
+        match try_script(tape, &script) {
+            Ok(damage) => return damage,
+            Err(report) => {
+                let hull = parse_failure(&report).expect("Unexpected droid report");
+                assert!(!hulls.contains(&hull), "Simulator disagrees with droid");
+                hulls.push(hull);
+            }
+        }
+    }
+}
 
 fn main() {
-    let _input = get_input(2019, 21);
+    let input = get_input(2019, 21);
+    let tape: Tape = parse_intcode_program(&input);
+
+    println!(
+        "Hull damage (walking): {}",
+        survey_hull_damage(&tape, Mode::Walk)
+    );
+    println!(
+        "Hull damage (running): {}",
+        survey_hull_damage(&tape, Mode::Run)
+    );
 }
@@ -1,5 +1,391 @@
 use aoc::aoc_input::get_input;
+use aoc::intcode::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+// Generous for any single command; an item that traps the droid in an
+// infinite loop blows through it
+const COMMAND_INSTRUCTION_LIMIT: usize = 10_000_000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::East => "east",
+            Direction::West => "west",
+        }
+    }
+
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "north" => Ok(Direction::North),
+            "south" => Ok(Direction::South),
+            "east" => Ok(Direction::East),
+            "west" => Ok(Direction::West),
+            _ => Err("Invalid direction"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Room {
+    name: String,
+    description: String,
+    doors: Vec<Direction>,
+    items: Vec<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Description,
+    Doors,
+    Items,
+    Other,
+}
+
+// Every room the output passes through, in order; being ejected from a room
+// shows both the room and the one the droid lands in
+fn parse_rooms(text: &str) -> Vec<Room> {
+    let mut rooms = Vec::<Room>::new();
+    let mut section = Section::Other;
+
+    for line in text.lines().map(|line| line.trim()) {
+        if let Some(name) = line.strip_prefix("== ").and_then(|l| l.strip_suffix(" ==")) {
+            rooms.push(Room {
+                name: name.to_string(),
+                description: String::new(),
+                doors: Vec::new(),
+                items: Vec::new(),
+            });
+            section = Section::Description;
+            continue;
+        }
+
+        let room = match rooms.last_mut() {
+            Some(room) => room,
+            None => continue,
+        };
+        match (line, line.strip_prefix("- ")) {
+            ("", _) => (),
+            ("Doors here lead:", _) => section = Section::Doors,
+            ("Items here:", _) => section = Section::Items,
+            (_, Some(entry)) if section == Section::Doors => {
+                if let Ok(door) = entry.parse() {
+                    room.doors.push(door);
+                }
+            }
+            (_, Some(entry)) if section == Section::Items => room.items.push(entry.to_string()),
+            _ if section == Section::Description && room.doors.is_empty() => {
+                if !room.description.is_empty() {
+                    room.description.push(' ');
+                }
+                room.description.push_str(line);
+            }
+            _ => section = Section::Other,
+        }
+    }
+    rooms
+}
+
+fn parse_room(text: &str) -> Option<Room> {
+    parse_rooms(text).pop()
+}
+
+fn parse_password(text: &str) -> Option<String> {
+    let (_, rest) = text.split_once("typing ")?;
+    let password: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    Some(password).filter(|p| !p.is_empty())
+}
+
+// The droid's machine state together with the room it is in
+struct SavedState {
+    machine: Snapshot,
+    current: String,
+}
+
+struct Explorer {
+    droid: AsciiIntcode,
+    rooms: HashMap<String, Room>,
+    doors: HashMap<(String, Direction), String>,
+    current: String,
+    // The room guarding the pressure-sensitive floor, and the way onto it
+    checkpoint: Option<(String, Direction)>,
+    inventory: Vec<String>,
+    deadly: HashSet<String>,
+}
+
+impl Explorer {
+    fn new(tape: Tape) -> Self {
+        let mut droid = AsciiIntcode::new(tape);
+        let output = droid.read_until_prompt().unwrap();
+        let room = parse_room(&output.text).expect("No starting room");
+
+        Explorer {
+            droid,
+            current: room.name.clone(),
+            rooms: HashMap::from([(room.name.clone(), room)]),
+            doors: HashMap::new(),
+            checkpoint: None,
+            inventory: Vec::new(),
+            deadly: HashSet::new(),
+        }
+    }
+
+    fn save(&self) -> SavedState {
+        SavedState {
+            machine: self.droid.machine.snapshot(),
+            current: self.current.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: &SavedState) {
+        self.droid.machine.restore(&state.machine);
+        self.current.clone_from(&state.current);
+    }
+
+    fn command(&mut self, command: &str) -> IntcodeResult<AsciiOutput> {
+        let machine = &mut self.droid.machine;
+        machine.set_instruction_limit(Some(
+            machine.instruction_count() + COMMAND_INSTRUCTION_LIMIT,
+        ));
+        self.droid.run_line(command)
+    }
+
+    fn go(&mut self, direction: Direction) -> Option<Room> {
+        let output = self.command(direction.name()).ok()?;
+        let room = parse_room(&output.text)?;
+        self.current = room.name.clone();
+        Some(room)
+    }
+
+    fn can_move(&mut self) -> bool {
+        let here = self.current.clone();
+        let door = self.rooms[&here].doors[0];
+        match self.go(door) {
+            Some(room) if room.name != here => self.go(door.opposite()).is_some(),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    // Some items end the game or trap the droid, so every take can be undone
+    fn take(&mut self, item: &str) {
+        let saved = self.save();
+        let safe = match self.command(&format!("take {}", item)) {
+            Ok(output) if !output.halted() => self.can_move(),
+            _ => false,
+        };
+
+        if safe {
+            self.inventory.push(item.to_string());
+        } else {
+            self.restore(&saved);
+            self.deadly.insert(item.to_string());
+        }
+    }
+
+    fn explore(&mut self) {
+        let room = self.rooms[&self.current].clone();
+        for item in room.items.iter() {
+            if !self.deadly.contains(item) {
+                self.take(item);
+            }
+        }
+
+        for &door in room.doors.iter() {
+            if self.doors.contains_key(&(room.name.clone(), door)) {
+                continue;
+            }
+
+            let next = self.go(door).expect("Droid got stuck");
+            if next.name == room.name {
+                self.checkpoint = Some((room.name.clone(), door));
+                continue;
+            }
+
+            self.doors
+                .insert((room.name.clone(), door), next.name.clone());
+            self.doors
+                .insert((next.name.clone(), door.opposite()), room.name.clone());
+            if !self.rooms.contains_key(&next.name) {
+                self.rooms.insert(next.name.clone(), next);
+                self.explore();
+            }
+            self.go(door.opposite()).expect("Droid got stuck");
+        }
+    }
+
+    fn path_to(&self, target: &str) -> Vec<Direction> {
+        let mut previous = HashMap::<&str, (&str, Direction)>::new();
+        let mut queue = VecDeque::from([self.current.as_str()]);
+
+        while let Some(room) = queue.pop_front() {
+            if room == target {
+                let mut path = Vec::new();
+                let mut room = room;
+                while let Some(&(prev, door)) = previous.get(room) {
+                    path.push(door);
+                    room = prev;
+                }
+                path.reverse();
+                return path;
+            }
+
+            for (&(ref from, door), to) in self.doors.iter() {
+                if from == room && to != &self.current && !previous.contains_key(to.as_str()) {
+                    previous.insert(to, (room, door));
+                    queue.push_back(to);
+                }
+            }
+        }
+        panic!("No path to {}", target);
+    }
+
+    // Walks each inventory subset onto the floor, changing one item per attempt
+    fn crack_airlock(&mut self) -> String {
+        let (checkpoint, floor) = self.checkpoint.clone().expect("No checkpoint found");
+        for door in self.path_to(&checkpoint) {
+            self.go(door).expect("Droid got stuck");
+        }
+
+        let items = self.inventory.clone();
+        let mut held = vec![true; items.len()];
+        for i in 0..1usize << items.len() {
+            if i > 0 {
+                let toggle = i.trailing_zeros() as usize;
+                held[toggle] = !held[toggle];
+                let verb = if held[toggle] { "take" } else { "drop" };
+                let output = self
+                    .command(&format!("{} {}", verb, items[toggle]))
+                    .unwrap();
+                assert!(!output.halted());
+            }
+
+            let output = self.command(floor.name()).unwrap();
+            if let Some(password) = parse_password(&output.text) {
+                return password;
+            }
+        }
+        panic!("No inventory opens the airlock");
+    }
+}
 
 fn main() {
-    let _input = get_input(2019, 25);
+    let input = get_input(2019, 25);
+    let tape: Tape = parse_intcode_program(&input);
+
+    if std::env::args().any(|arg| arg == "play") {
+        AsciiIntcode::new(tape).interactive().unwrap();
+        return;
+    }
+
+    let mut explorer = Explorer::new(tape);
+    explorer.explore();
+    println!("Items collected: {}", explorer.inventory.join(", "));
+    println!("Airlock password: {}", explorer.crack_airlock());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HULL_BREACH: &str = "\n\n\n\
+        == Hull Breach ==\n\
+        You got in through a hole in the floor here. To keep your ship from also freezing, the hole has been sealed.\n\
+        \n\
+        Doors here lead:\n\
+        - north\n\
+        - east\n\
+        - west\n\
+        \n\
+        Items here:\n\
+        - mutex\n\
+        - giant electromagnet\n\
+        \n\
+        Command?\n";
+
+    const EJECTED: &str = "\n\n\n\
+        == Pressure-Sensitive Floor ==\n\
+        Analyzing...\n\
+        \n\
+        Doors here lead:\n\
+        - south\n\
+        \n\
+        A loud, robotic voice says \"Alert! Droids on this ship are heavier than the detected value!\" and you are ejected back to the checkpoint.\n\
+        \n\n\n\
+        == Security Checkpoint ==\n\
+        In the next room, a pressure-sensitive floor will verify your identity.\n\
+        \n\
+        Doors here lead:\n\
+        - north\n\
+        - west\n\
+        \n\
+        Command?\n";
+
+    const UNLOCKED: &str = "\n\n\n\
+        == Pressure-Sensitive Floor ==\n\
+        Analyzing...\n\
+        \n\
+        Doors here lead:\n\
+        - south\n\
+        \n\
+        A loud, robotic voice says \"Analysis complete! You may proceed.\" and you enter the cockpit.\n\
+        Santa notices your small droid, looks puzzled for a moment, realizes what has happened, and radios your ship directly.\n\
+        \"Oh, hello! You should be able to get in by typing 2424308736 on the keypad at the main airlock.\"\n";
+
+    #[test]
+    fn test_parse_room() {
+        let room = parse_room(HULL_BREACH).unwrap();
+        assert_eq!(room.name, "Hull Breach");
+        assert!(room.description.starts_with("You got in through a hole"));
+        assert_eq!(
+            room.doors,
+            [Direction::North, Direction::East, Direction::West]
+        );
+        assert_eq!(room.items, ["mutex", "giant electromagnet"]);
+
+        assert_eq!(parse_room("\nYou take the mutex.\n\nCommand?\n"), None);
+    }
+
+    #[test]
+    fn test_parse_ejection() {
+        let rooms = parse_rooms(EJECTED);
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].name, "Pressure-Sensitive Floor");
+        assert_eq!(rooms[0].description, "Analyzing...");
+        assert_eq!(rooms[0].doors, [Direction::South]);
+
+        let checkpoint = &rooms[1];
+        assert_eq!(checkpoint.name, "Security Checkpoint");
+        assert_eq!(checkpoint.doors, [Direction::North, Direction::West]);
+        assert!(checkpoint.items.is_empty());
+        assert_eq!(parse_password(EJECTED), None);
+    }
+
+    #[test]
+    fn test_parse_password() {
+        assert_eq!(parse_password(UNLOCKED).as_deref(), Some("2424308736"));
+    }
 }
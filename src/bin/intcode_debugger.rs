@@ -0,0 +1,242 @@
+use aoc::aoc_input::get_input;
+use aoc::intcode::disasm::{decode_at, InstructionKind};
+use aoc::intcode::*;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  c                   continue until halt, input, breakpoint or watchpoint
+  s [n]               step n instructions (default 1)
+  b <addr>            set a breakpoint
+  w <addr> [r|w|rw]   set a watchpoint (default rw)
+  d <addr>            delete a breakpoint or watchpoint
+  i                   list breakpoints and watchpoints
+  r                   show registers
+  l [addr] [n]        disassemble n instructions (default at pc)
+  x <addr> [n]        examine n memory words
+  cfg [addr]          print the control-flow graph from addr as DOT
+  set <addr> <value>  write a memory word
+  in <text>           queue a line of ASCII input
+  num <values...>     queue numeric input
+  trace on|off        print each executed instruction
+  limit <n>|off       stop after n more instructions
+  q                   quit";
+
+fn load_program() -> Tape {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let input = match args.as_slice() {
+        [command, day] if command == "day" => get_input(2019, day.parse().expect("Invalid day")),
+        [path] => std::fs::read_to_string(path).expect("Failed reading program"),
+        _ => {
+            eprintln!("usage: intcode_debugger <program file> | day <n>");
+            std::process::exit(1);
+        }
+    };
+    parse_intcode_program(&input)
+}
+
+fn parse_num<T: std::str::FromStr>(arg: Option<&&str>) -> Result<T, String> {
+    let arg = arg.ok_or("Missing argument")?;
+    arg.parse().map_err(|_| format!("Invalid number: {}", arg))
+}
+
+fn drain_output(machine: &IntcodeMachine) {
+    let output: Vec<_> = machine.output.borrow_mut().drain(..).collect();
+    if output.is_empty() {
+        return;
+    }
+
+    if output
+        .iter()
+        .all(|&c| c == '\n' as isize || (32..127).contains(&c))
+    {
+        print!(
+            "{}",
+            output.iter().map(|&c| c as u8 as char).collect::<String>()
+        );
+    } else {
+        println!("output: {:?}", output);
+    }
+}
+
+fn report(machine: &IntcodeMachine, status: IntcodeResult<Option<StopStatus>>) {
+    drain_output(machine);
+    match status {
+        Ok(None) => (),
+        Ok(Some(StopStatus::Halted)) => println!("halted at {}", machine.pc()),
+        Ok(Some(StopStatus::BlockedOnInput)) => println!("waiting for input at {}", machine.pc()),
+        Ok(Some(StopStatus::Breakpoint(addr))) => println!("breakpoint at {}", addr),
+        Ok(Some(StopStatus::Watchpoint(hit))) => println!(
+            "watchpoint: {:?} of [{}] by instruction at {}",
+            hit.access, hit.addr, hit.pc
+        ),
+        Err(e) => println!("error: {:?}", e),
+    }
+    println!("{} instructions executed", machine.instruction_count());
+    print_current(machine);
+}
+
+fn print_current(machine: &IntcodeMachine) {
+    let pc = machine.pc();
+    match decode_at(machine.tape(), pc) {
+        Some(opcode) => println!("{:>5}: {}", pc, opcode),
+        None => println!("{:>5}: <invalid instruction>", pc),
+    }
+}
+
+fn execute(machine: &mut IntcodeMachine, line: &str) -> Result<bool, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+        Some((command, args)) => (*command, args),
+        None => return Ok(true),
+    };
+
+    match command {
+        "c" => {
+            let status = machine.run().map(Some);
+            report(machine, status);
+        }
+        "s" => {
+            let count = if args.is_empty() {
+                1
+            } else {
+                parse_num(args.first())?
+            };
+            let mut status = Ok(None);
+            for _ in 0..count {
+                status = machine.step();
+                if !matches!(status, Ok(None)) {
+                    break;
+                }
+            }
+            report(machine, status);
+        }
+        "b" => machine.add_breakpoint(parse_num(args.first())?),
+        "w" => {
+            let kind = match args.get(1).copied().unwrap_or("rw") {
+                "r" => WatchKind::Read,
+                "w" => WatchKind::Write,
+                "rw" => WatchKind::ReadWrite,
+                other => return Err(format!("Invalid watch kind: {}", other)),
+            };
+            machine.add_watchpoint(parse_num(args.first())?, kind);
+        }
+        "d" => {
+            let addr = parse_num(args.first())?;
+            let removed = machine.remove_breakpoint(addr) | machine.remove_watchpoint(addr);
+            if !removed {
+                return Err(format!("Nothing set at {}", addr));
+            }
+        }
+        "i" => {
+            for addr in machine.breakpoints() {
+                println!("breakpoint {}", addr);
+            }
+            for (addr, kind) in machine.watchpoints() {
+                println!("watchpoint {} {:?}", addr, kind);
+            }
+        }
+        "r" => println!("pc={} bp={}", machine.pc(), machine.bp()),
+        "l" => {
+            let addr = match args.first() {
+                Some(_) => parse_num(args.first())?,
+                None => machine.pc(),
+            };
+            let count: usize = match args.get(1) {
+                Some(_) => parse_num(args.get(1))?,
+                None => 10,
+            };
+            let tape = machine.tape();
+            let mut addr = addr;
+            for _ in 0..count {
+                let kind = match decode_at(tape, addr) {
+                    Some(opcode) => InstructionKind::Op(opcode),
+                    None if addr < tape.len() => InstructionKind::Data(tape[addr]),
+                    None => break,
+                };
+                let instruction = Instruction { addr, kind };
+                println!("{}", instruction);
+                addr = instruction.next_addr();
+            }
+        }
+        "x" => {
+            let addr: isize = parse_num(args.first())?;
+            let count: isize = match args.get(1) {
+                Some(_) => parse_num(args.get(1))?,
+                None => 1,
+            };
+            for a in addr..addr + count {
+                let value = machine.read_addr(a).map_err(|e| format!("{:?}", e))?;
+                println!("{:>5}: {}", a, value);
+            }
+        }
+        "cfg" => {
+            let entry = match args.first() {
+                Some(_) => parse_num(args.first())?,
+                None => machine.pc(),
+            };
+            let cfg = ControlFlowGraph::build(machine.tape(), entry);
+            print!("{}", cfg.to_dot());
+        }
+        "set" => {
+            let addr = parse_num(args.first())?;
+            let value = parse_num(args.get(1))?;
+            machine
+                .write_addr(addr, value)
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        "in" => {
+            let text = line.trim()[command.len()..].trim_start();
+            let mut input = machine.input.borrow_mut();
+            input.extend(text.chars().map(|c| c as isize));
+            input.push_back('\n' as isize);
+        }
+        "num" => {
+            for arg in args {
+                let value = arg
+                    .parse()
+                    .map_err(|_| format!("Invalid number: {}", arg))?;
+                machine.input.borrow_mut().push_back(value);
+            }
+        }
+        "trace" => match args.first().copied() {
+            Some("on") => machine.set_tracer(|event: &TraceEvent| println!("{}", event)),
+            Some("off") => machine.clear_tracer(),
+            _ => return Err("Usage: trace on|off".to_string()),
+        },
+        "limit" => match args.first().copied() {
+            Some("off") => machine.set_instruction_limit(None),
+            _ => {
+                let count: usize = parse_num(args.first())?;
+                machine.set_instruction_limit(Some(machine.instruction_count() + count));
+            }
+        },
+        "h" | "help" => println!("{}", HELP),
+        "q" => return Ok(false),
+        _ => return Err(format!("Unknown command: {} (try 'help')", command)),
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    let mut machine = IntcodeMachine::new(load_program());
+    print_current(&machine);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+
+        match execute(&mut machine, &line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const FIRST_YEAR: u16 = 2015;
+const SHORT_CALENDAR_YEAR: u16 = 2025;
+const UNLOCK_HOUR_UTC: u64 = 5;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarError {
+    InvalidYear(u16),
+    InvalidDay(u16, u8),
+    NotYetUnlocked(Duration),
+}
+
+pub fn days_in_year(year: u16) -> u8 {
+    if year >= SHORT_CALENDAR_YEAR {
+        12
+    } else {
+        25
+    }
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+pub fn validate(year: u16, day: u8) -> Result<(), CalendarError> {
+    if year < FIRST_YEAR {
+        return Err(CalendarError::InvalidYear(year));
+    }
+    if day == 0 || day > days_in_year(year) {
+        return Err(CalendarError::InvalidDay(year, day));
+    }
+    Ok(())
+}
+
+// Puzzles unlock at midnight US Eastern, which is always EST (UTC-5) in December
+pub fn unlock_ts(year: u16, day: u8) -> u64 {
+    let days = days_from_civil(year as i64, 12, day as i64) as u64;
+    days * SECS_PER_DAY + UNLOCK_HOUR_UTC * 60 * 60
+}
+
+pub fn unlock_time(year: u16, day: u8) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(unlock_ts(year, day))
+}
+
+pub fn time_until_unlock_at(year: u16, day: u8, now: SystemTime) -> Option<Duration> {
+    unlock_time(year, day).duration_since(now).ok()
+}
+
+pub fn time_until_unlock(year: u16, day: u8) -> Option<Duration> {
+    time_until_unlock_at(year, day, SystemTime::now())
+}
+
+pub fn check_unlocked_at(year: u16, day: u8, now: SystemTime) -> Result<(), CalendarError> {
+    validate(year, day)?;
+    match time_until_unlock_at(year, day, now) {
+        Some(remaining) if !remaining.is_zero() => Err(CalendarError::NotYetUnlocked(remaining)),
+        _ => Ok(()),
+    }
+}
+
+pub fn check_unlocked(year: u16, day: u8) -> Result<(), CalendarError> {
+    check_unlocked_at(year, day, SystemTime::now())
+}
+
+pub fn wait_until_unlock(year: u16, day: u8) -> Result<(), CalendarError> {
+    validate(year, day)?;
+    while let Some(remaining) = time_until_unlock(year, day) {
+        if remaining.is_zero() {
+            break;
+        }
+        std::thread::sleep(remaining);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(2014, 1), Err(CalendarError::InvalidYear(2014)));
+        assert_eq!(validate(2019, 0), Err(CalendarError::InvalidDay(2019, 0)));
+        assert_eq!(validate(2019, 26), Err(CalendarError::InvalidDay(2019, 26)));
+        assert_eq!(validate(2025, 13), Err(CalendarError::InvalidDay(2025, 13)));
+        assert_eq!(validate(2015, 1), Ok(()));
+        assert_eq!(validate(2024, 25), Ok(()));
+        assert_eq!(validate(2025, 12), Ok(()));
+    }
+
+    #[test]
+    fn test_unlock_ts() {
+        assert_eq!(unlock_ts(2015, 1), 1448946000);
+        assert_eq!(unlock_ts(2021, 1), 1638334800);
+        assert_eq!(unlock_ts(2021, 2), 1638421200);
+        assert_eq!(unlock_ts(2024, 25), 1735102800);
+    }
+
+    #[test]
+    fn test_check_unlocked() {
+        let unlock = unlock_time(2021, 5);
+        let before = unlock - Duration::from_secs(90);
+        assert_eq!(
+            check_unlocked_at(2021, 5, before),
+            Err(CalendarError::NotYetUnlocked(Duration::from_secs(90)))
+        );
+        assert_eq!(check_unlocked_at(2021, 5, unlock), Ok(()));
+        assert_eq!(
+            check_unlocked_at(2021, 5, unlock + Duration::from_secs(1)),
+            Ok(())
+        );
+        assert_eq!(check_unlocked(2019, 25), Ok(()));
+        assert_eq!(wait_until_unlock(2019, 25), Ok(()));
+    }
+}
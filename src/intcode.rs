@@ -1,7 +1,38 @@
-use crate::digits::digits;
+pub mod ascii;
+pub mod asm;
+pub mod cache;
+pub mod cfg;
+#[cfg(test)]
+mod conformance;
+pub mod disasm;
+pub mod io;
+pub mod memory;
+pub mod network;
+pub mod profile;
+pub mod snapshot;
+pub mod trace;
+pub mod word;
+
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::rc::Rc;
+use std::sync::Arc;
+
+pub use ascii::{AsciiIntcode, AsciiOutput};
+pub use asm::assemble;
+pub use cache::DecodedTape;
+pub use cfg::ControlFlowGraph;
+pub use disasm::{disassemble, Instruction};
+pub use io::{spawn_machine, Channel, ChannelMachine, IntcodeIo};
+pub use memory::{DenseMemory, Memory, PagedMemory};
+pub use network::{Network, NetworkStatus, Packet, Policy, Router};
+pub use profile::Profiler;
+pub use snapshot::Snapshot;
+pub use trace::{TraceEvent, TraceSink};
+pub use word::Word;
+
+use cache::DecodeCache;
+use trace::Tracer;
 
 #[derive(Debug)]
 pub enum IntcodeError {
@@ -11,29 +42,65 @@ pub enum IntcodeError {
     NegativeAddress,
     InvalidStoreAddressingMode,
     DidNotRunToCompletion,
+    InstructionLimitExceeded,
+    ArithmeticOverflow,
+    AddressOutOfRange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    fn matches(&self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, Access::Read)
+                | (WatchKind::Write, Access::Write)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub pc: usize,
+    pub addr: usize,
+    pub access: Access,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StopStatus {
     Halted,
     BlockedOnInput,
+    Breakpoint(usize),
+    Watchpoint(WatchHit),
 }
 
-#[derive(Debug)]
-enum AddressingMode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
     AbsoluteAddress,
     Immediate,
     BasePointerRelative,
 }
 
-#[derive(Debug)]
-struct Operand {
-    mode: AddressingMode,
-    value: isize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand<W = isize> {
+    pub mode: AddressingMode,
+    pub value: W,
 }
 
-#[derive(Debug)]
-enum Operation {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
     Add,
     Multiply,
     Input,
@@ -46,24 +113,53 @@ enum Operation {
     Halt,
 }
 
-#[derive(Debug)]
-struct Opcode {
-    operation: Operation,
-    operands: Vec<Operand>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opcode<W = isize> {
+    pub operation: Operation,
+    pub operands: Vec<Operand<W>>,
 }
 
 pub type IntcodeResult<T> = Result<T, IntcodeError>;
-pub type Tape = Vec<isize>;
-pub type Stream = VecDeque<isize>;
-pub type StreamRef = Rc<RefCell<Stream>>;
+pub type Tape<W = isize> = Vec<W>;
+pub type Stream<W = isize> = VecDeque<W>;
+pub type StreamRef<W = isize> = Rc<RefCell<Stream<W>>>;
 
 #[derive(Debug)]
-pub struct IntcodeMachine {
-    tape: Tape,
-    pub input: StreamRef,
-    pub output: StreamRef,
+pub struct IntcodeMachine<W = isize, M = DenseMemory<W>, S = StreamRef<W>> {
+    memory: M,
+    max_address: usize,
+    pub input: S,
+    pub output: S,
     pc: isize,
     bp: isize,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, WatchKind>,
+    watch_hit: Option<(usize, Access)>,
+    resume_pc: Option<isize>,
+    tracer: Option<Tracer<W>>,
+    trace_event: Option<TraceEvent<W>>,
+    instruction_count: usize,
+    instruction_limit: Option<usize>,
+    // Stopped on a halt that was already counted
+    halted: bool,
+    decode_cache: DecodeCache<W>,
+}
+
+impl Operation {
+    pub fn operand_count(&self) -> usize {
+        match self {
+            Operation::Add => 3,
+            Operation::Multiply => 3,
+            Operation::Input => 1,
+            Operation::Output => 1,
+            Operation::JumpTrue => 2,
+            Operation::JumpFalse => 2,
+            Operation::LessThan => 3,
+            Operation::Equals => 3,
+            Operation::AdjustBasePointer => 1,
+            Operation::Halt => 0,
+        }
+    }
 }
 
 fn parse_addressing_mode(digit: usize) -> IntcodeResult<AddressingMode> {
@@ -75,78 +171,220 @@ fn parse_addressing_mode(digit: usize) -> IntcodeResult<AddressingMode> {
     }
 }
 
-impl IntcodeMachine {
-    pub fn new_io(tape: Tape, input: StreamRef, output: StreamRef) -> Self {
+pub fn decode_opcode(opcode: isize) -> IntcodeResult<(Operation, [AddressingMode; 3])> {
+    if opcode < 0 {
+        return Err(IntcodeError::NegativeOpcode);
+    }
+
+    let opcode = opcode as usize;
+    if opcode >= 100_000 {
+        return Err(IntcodeError::InvalidAddressingMode);
+    }
+
+    let operation = match opcode % 100 {
+        1 => Operation::Add,
+        2 => Operation::Multiply,
+        3 => Operation::Input,
+        4 => Operation::Output,
+        5 => Operation::JumpTrue,
+        6 => Operation::JumpFalse,
+        7 => Operation::LessThan,
+        8 => Operation::Equals,
+        9 => Operation::AdjustBasePointer,
+        99 => Operation::Halt,
+        _ => return Err(IntcodeError::InvalidOpcodeOperation),
+    };
+
+    let modes = [
+        parse_addressing_mode(opcode / 100 % 10)?,
+        parse_addressing_mode(opcode / 1000 % 10)?,
+        parse_addressing_mode(opcode / 10000 % 10)?,
+    ];
+    Ok((operation, modes))
+}
+
+fn to_addr<W: Word>(value: &W) -> IntcodeResult<isize> {
+    match value.to_isize() {
+        Some(addr) => Ok(addr),
+        None if value.is_negative() => Err(IntcodeError::NegativeAddress),
+        None => Err(IntcodeError::AddressOutOfRange),
+    }
+}
+
+impl<W: Word> IntcodeMachine<W> {
+    pub fn new_io(tape: Tape<W>, input: StreamRef<W>, output: StreamRef<W>) -> Self {
+        Self::with_memory(DenseMemory::from_tape(tape), input, output)
+    }
+
+    pub fn new(tape: Tape<W>) -> Self {
+        Self::new_io(tape, new_stream_ref(), new_stream_ref())
+    }
+}
+
+impl<W: Word, S: IntcodeIo<W>> IntcodeMachine<W, DenseMemory<W>, S> {
+    pub fn tape(&self) -> &Tape<W> {
+        self.memory.tape()
+    }
+}
+
+impl<W: Word> IntcodeMachine<W, PagedMemory<W>> {
+    pub fn new_paged(tape: Tape<W>) -> Self {
+        Self::with_memory(
+            PagedMemory::from_tape(tape),
+            new_stream_ref(),
+            new_stream_ref(),
+        )
+    }
+}
+
+impl<W: Word, M: Memory<W>, S: IntcodeIo<W>> IntcodeMachine<W, M, S> {
+    pub fn with_memory(memory: M, input: S, output: S) -> Self {
         IntcodeMachine {
-            tape,
+            memory,
+            max_address: M::DEFAULT_MAX_ADDRESS,
             input,
             output,
             pc: 0,
             bp: 0,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            watch_hit: None,
+            resume_pc: None,
+            tracer: None,
+            trace_event: None,
+            instruction_count: 0,
+            instruction_limit: None,
+            halted: false,
+            decode_cache: DecodeCache::new(),
         }
     }
 
-    pub fn new(tape: Tape) -> Self {
-        Self::new_io(tape, new_stream_ref(), new_stream_ref())
+    // Starts on a tape decoded up front, sharing the decoded instructions with
+    // every other machine started from it
+    pub fn from_decoded(decoded: &DecodedTape<W>, input: S, output: S) -> Self {
+        let mut machine = Self::with_memory(M::from_tape(decoded.tape().clone()), input, output);
+        machine.decode_cache = DecodeCache::from_decoded(decoded);
+        machine
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc as usize
+    }
+
+    pub fn bp(&self) -> isize {
+        self.bp
+    }
+
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    pub fn set_max_address(&mut self, max_address: usize) {
+        self.max_address = max_address;
+        self.decode_cache.clear();
+    }
+
+    pub fn add_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: usize, kind: WatchKind) {
+        self.watchpoints.insert(addr, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (usize, WatchKind)> + '_ {
+        self.watchpoints.iter().map(|(&addr, &kind)| (addr, kind))
+    }
+
+    pub fn set_tracer(&mut self, sink: impl TraceSink<W> + Send + 'static) {
+        self.tracer = Some(Tracer(Box::new(sink)));
+    }
+
+    pub fn clear_tracer(&mut self) {
+        self.tracer = None;
+    }
+
+    pub fn set_instruction_limit(&mut self, limit: Option<usize>) {
+        self.instruction_limit = limit;
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.instruction_count
+    }
+
+    // On by default; off decodes every instruction each time it runs
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache.set_enabled(enabled);
+    }
+
+    pub fn decode_cache_enabled(&self) -> bool {
+        self.decode_cache.is_enabled()
     }
 
-    fn verify_addr(&mut self, addr: isize) -> IntcodeResult<usize> {
+    fn check_watchpoint(&mut self, addr: usize, access: Access) {
+        if self.watch_hit.is_some() || self.watchpoints.is_empty() {
+            return;
+        }
+        if let Some(kind) = self.watchpoints.get(&addr) {
+            if kind.matches(access) {
+                self.watch_hit = Some((addr, access));
+            }
+        }
+    }
+
+    fn verify_addr(&self, addr: isize) -> IntcodeResult<usize> {
         if addr < 0 {
             return Err(IntcodeError::NegativeAddress);
         }
 
         let addr = addr as usize;
-        if addr >= self.tape.len() {
-            self.tape.resize(addr + 1, 0);
+        if addr > self.max_address {
+            return Err(IntcodeError::AddressOutOfRange);
         }
         Ok(addr)
     }
 
-    pub fn read_addr(&mut self, addr: isize) -> IntcodeResult<isize> {
+    pub fn read_addr(&mut self, addr: isize) -> IntcodeResult<W> {
         let addr = self.verify_addr(addr)?;
-        Ok(self.tape[addr])
+        Ok(self.memory.read(addr))
     }
 
-    fn write_addr(&mut self, addr: isize, value: isize) -> IntcodeResult<()> {
+    pub fn write_addr(&mut self, addr: isize, value: W) -> IntcodeResult<()> {
         let addr = self.verify_addr(addr)?;
-        self.tape[addr] = value;
+        self.decode_cache.invalidate(addr);
+        self.memory.write(addr, value);
         Ok(())
     }
 
-    fn read_pc(&mut self) -> IntcodeResult<isize> {
+    fn read_pc(&mut self) -> IntcodeResult<W> {
         let value = self.read_addr(self.pc)?;
         self.pc += 1;
         Ok(value)
     }
 
-    fn read_opcode(&mut self) -> IntcodeResult<Opcode> {
+    fn read_opcode(&mut self) -> IntcodeResult<Opcode<W>> {
         let opcode = self.read_pc()?;
-        if opcode < 0 {
-            return Err(IntcodeError::NegativeOpcode);
-        }
+        let opcode = opcode
+            .to_isize()
+            .ok_or(IntcodeError::InvalidOpcodeOperation)?;
+        let (operation, modes) = decode_opcode(opcode)?;
 
-        let mut digits: Vec<_> = digits(opcode as usize, 10).collect();
-        digits.extend(vec![0; 5 - digits.len()]);
-
-        let (operation, operand_count) = match 10 * digits[1] + digits[0] {
-            1 => (Operation::Add, 3),
-            2 => (Operation::Multiply, 3),
-            3 => (Operation::Input, 1),
-            4 => (Operation::Output, 1),
-            5 => (Operation::JumpTrue, 2),
-            6 => (Operation::JumpFalse, 2),
-            7 => (Operation::LessThan, 3),
-            8 => (Operation::Equals, 3),
-            9 => (Operation::AdjustBasePointer, 1),
-            99 => (Operation::Halt, 0),
-            _ => return Err(IntcodeError::InvalidOpcodeOperation),
-        };
-
-        let mut operands = Vec::<Operand>::new();
-        for i in 0..operand_count {
-            let mode = parse_addressing_mode(digits[2 + i])?;
+        let mut operands = Vec::<Operand<W>>::new();
+        for mode in modes.iter().take(operation.operand_count()) {
             let value = self.read_pc()?;
-            operands.push(Operand { mode, value });
+            operands.push(Operand { mode: *mode, value });
         }
 
         Ok(Opcode {
@@ -155,46 +393,114 @@ impl IntcodeMachine {
         })
     }
 
-    fn load(&mut self, op: &Operand) -> IntcodeResult<isize> {
-        match op.mode {
-            AddressingMode::AbsoluteAddress => Ok(self.read_addr(op.value)?),
-            AddressingMode::Immediate => Ok(op.value),
-            AddressingMode::BasePointerRelative => Ok(self.read_addr(self.bp + op.value)?),
+    fn fetch(&mut self) -> IntcodeResult<Arc<Opcode<W>>> {
+        let addr = self.pc as usize;
+        if let Some(opcode) = self.decode_cache.get(addr) {
+            self.pc += 1 + opcode.operands.len() as isize;
+            return Ok(opcode);
+        }
+
+        let cache = self.decode_cache.visit(addr);
+        let opcode = Arc::new(self.read_opcode()?);
+        if cache {
+            self.decode_cache.insert(addr, opcode.clone());
         }
+        Ok(opcode)
     }
 
-    fn store(&mut self, op: &Operand, value: isize) -> IntcodeResult<()> {
+    fn effective_addr(&self, op: &Operand<W>) -> IntcodeResult<isize> {
+        let addr = to_addr(&op.value)?;
         match op.mode {
-            AddressingMode::AbsoluteAddress => Ok(self.write_addr(op.value, value)?),
-            AddressingMode::BasePointerRelative => Ok(self.write_addr(self.bp + op.value, value)?),
-            AddressingMode::Immediate => Err(IntcodeError::InvalidStoreAddressingMode),
+            AddressingMode::BasePointerRelative => self
+                .bp
+                .checked_add(addr)
+                .ok_or(IntcodeError::ArithmeticOverflow),
+            _ => Ok(addr),
         }
     }
 
-    fn jump_conditional(&mut self, condition: bool, target: isize) -> IntcodeResult<()> {
+    fn load(&mut self, op: &Operand<W>) -> IntcodeResult<W> {
+        let value = if op.mode == AddressingMode::Immediate {
+            op.value.clone()
+        } else {
+            let addr = self.verify_addr(self.effective_addr(op)?)?;
+            self.check_watchpoint(addr, Access::Read);
+            self.memory.read(addr)
+        };
+
+        if let Some(event) = self.trace_event.as_mut() {
+            event.loads.push(value.clone());
+        }
+        Ok(value)
+    }
+
+    fn store(&mut self, op: &Operand<W>, value: W) -> IntcodeResult<()> {
+        if op.mode == AddressingMode::Immediate {
+            return Err(IntcodeError::InvalidStoreAddressingMode);
+        }
+
+        let addr = self.verify_addr(self.effective_addr(op)?)?;
+        self.check_watchpoint(addr, Access::Write);
+        if let Some(event) = self.trace_event.as_mut() {
+            event.store = Some((addr, value.clone()));
+        }
+        self.decode_cache.invalidate(addr);
+        self.memory.write(addr, value);
+        Ok(())
+    }
+
+    fn jump_conditional(&mut self, condition: bool, target: W) -> IntcodeResult<()> {
         if condition {
+            let target = to_addr(&target)?;
             self.verify_addr(target)?;
             self.pc = target;
         }
         Ok(())
     }
 
-    fn tick(&mut self) -> IntcodeResult<Option<StopStatus>> {
+    fn finish_instruction(&mut self) {
+        self.instruction_count += 1;
+        if let (Some(tracer), Some(event)) = (self.tracer.as_mut(), self.trace_event.take()) {
+            tracer.0.record(&event);
+        }
+    }
+
+    pub fn step(&mut self) -> IntcodeResult<Option<StopStatus>> {
+        // Re-running an already counted halt does no work, so it is never over the limit
+        let limit = self.instruction_limit.filter(|_| !self.halted);
+        if limit.is_some_and(|limit| self.instruction_count >= limit) {
+            return Err(IntcodeError::InstructionLimitExceeded);
+        }
+
         let start_pc = self.pc;
-        let opcode = self.read_opcode()?;
+        self.watch_hit = None;
+        let opcode = self.fetch()?;
+        self.trace_event = self.tracer.as_ref().map(|_| TraceEvent {
+            pc: start_pc as usize,
+            opcode: (*opcode).clone(),
+            loads: Vec::new(),
+            store: None,
+        });
 
         match opcode.operation {
             Operation::Add => {
-                let value = self.load(&opcode.operands[0])? + self.load(&opcode.operands[1])?;
+                let lhs = self.load(&opcode.operands[0])?;
+                let rhs = self.load(&opcode.operands[1])?;
+                let value = lhs
+                    .checked_add(&rhs)
+                    .ok_or(IntcodeError::ArithmeticOverflow)?;
                 self.store(&opcode.operands[2], value)?;
             }
             Operation::Multiply => {
-                let value = self.load(&opcode.operands[0])? * self.load(&opcode.operands[1])?;
+                let lhs = self.load(&opcode.operands[0])?;
+                let rhs = self.load(&opcode.operands[1])?;
+                let value = lhs
+                    .checked_mul(&rhs)
+                    .ok_or(IntcodeError::ArithmeticOverflow)?;
                 self.store(&opcode.operands[2], value)?;
             }
             Operation::Input => {
-                let input = self.input.borrow_mut().pop_front();
-                match input {
+                match self.input.try_read() {
                     Some(value) => self.store(&opcode.operands[0], value)?,
                     None => {
                         self.pc = start_pc;
@@ -204,43 +510,71 @@ impl IntcodeMachine {
             }
             Operation::Output => {
                 let value = self.load(&opcode.operands[0])?;
-                self.output.borrow_mut().push_back(value);
+                self.output.write(value);
             }
             Operation::JumpTrue => {
                 let condition = self.load(&opcode.operands[0])?;
                 let target = self.load(&opcode.operands[1])?;
-                self.jump_conditional(condition != 0, target)?;
+                self.jump_conditional(!condition.is_zero(), target)?;
             }
             Operation::JumpFalse => {
                 let condition = self.load(&opcode.operands[0])?;
                 let target = self.load(&opcode.operands[1])?;
-                self.jump_conditional(condition == 0, target)?;
+                self.jump_conditional(condition.is_zero(), target)?;
             }
             Operation::LessThan => {
                 let value = self.load(&opcode.operands[0])? < self.load(&opcode.operands[1])?;
-                self.store(&opcode.operands[2], value as isize)?;
+                self.store(&opcode.operands[2], W::from_isize(value as isize))?;
             }
             Operation::Equals => {
                 let value = self.load(&opcode.operands[0])? == self.load(&opcode.operands[1])?;
-                self.store(&opcode.operands[2], value as isize)?;
+                self.store(&opcode.operands[2], W::from_isize(value as isize))?;
             }
             Operation::AdjustBasePointer => {
-                let newbp = self.bp + self.load(&opcode.operands[0])?;
+                let offset = to_addr(&self.load(&opcode.operands[0])?)?;
+                let newbp = self
+                    .bp
+                    .checked_add(offset)
+                    .ok_or(IntcodeError::ArithmeticOverflow)?;
                 self.verify_addr(newbp)?;
                 self.bp = newbp;
             }
             Operation::Halt => {
                 self.pc = start_pc;
+                if self.halted {
+                    self.trace_event = None;
+                } else {
+                    self.halted = true;
+                    self.finish_instruction();
+                }
                 return Ok(Some(StopStatus::Halted));
             }
         };
 
-        Ok(None)
+        self.halted = false;
+        self.finish_instruction();
+
+        let hit = self.watch_hit.take().map(|(addr, access)| WatchHit {
+            pc: start_pc as usize,
+            addr,
+            access,
+        });
+        Ok(hit.map(StopStatus::Watchpoint))
     }
 
     pub fn run(&mut self) -> IntcodeResult<StopStatus> {
+        // Resuming from a breakpoint executes the instruction it stopped on
+        let mut resume_pc = self.resume_pc.take();
         loop {
-            match self.tick() {
+            if !self.breakpoints.is_empty() && resume_pc.take() != Some(self.pc) {
+                let pc = self.pc as usize;
+                if self.breakpoints.contains(&pc) {
+                    self.resume_pc = Some(self.pc);
+                    return Ok(StopStatus::Breakpoint(pc));
+                }
+            }
+
+            match self.step() {
                 Ok(None) => continue,
                 Ok(Some(status)) => return Ok(status),
                 Err(e) => return Err(e),
@@ -256,26 +590,252 @@ impl IntcodeMachine {
     }
 }
 
-pub fn new_stream_ref() -> StreamRef {
+pub fn new_stream_ref<W>() -> StreamRef<W> {
     Rc::new(RefCell::new(Stream::new()))
 }
 
-pub fn new_stream_ref_from(value: isize) -> StreamRef {
+pub fn new_stream_ref_from<W>(value: W) -> StreamRef<W> {
     let s = new_stream_ref();
     s.borrow_mut().push_back(value);
     s
 }
 
-pub fn new_stream_ref_from_iter(iter: impl IntoIterator<Item = isize>) -> StreamRef {
+pub fn new_stream_ref_from_iter<W>(iter: impl IntoIterator<Item = W>) -> StreamRef<W> {
     let s = new_stream_ref();
     s.borrow_mut().extend(iter);
     s
 }
 
-pub fn parse_intcode_program(input: &str) -> Tape {
+pub fn parse_intcode_program<W: Word>(input: &str) -> Tape<W> {
     input
         .trim()
         .split(',')
-        .map(|s| s.parse().unwrap())
-        .collect::<Tape>()
+        .map(|s| {
+            s.parse()
+                .unwrap_or_else(|_| panic!("Invalid Intcode word {:?}", s))
+        })
+        .collect::<Tape<W>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn counter_machine() -> IntcodeMachine {
+        let tape = assemble(
+            "
+            loop:   add [n], #1, [n]
+                    out [n]
+                    lt [n], #3, [flag]
+                    jt [flag], #loop
+                    hlt
+            n:      .data 0
+            flag:   .data 0
+            ",
+        )
+        .unwrap();
+        IntcodeMachine::new(tape)
+    }
+
+    #[test]
+    fn test_step() {
+        let mut machine = counter_machine();
+        assert_eq!(machine.step().unwrap(), None);
+        assert_eq!(machine.pc(), 4);
+        assert_eq!(machine.tape()[14], 1);
+        assert_eq!(machine.step().unwrap(), None);
+        assert_eq!(machine.output.borrow_mut().pop_front(), Some(1));
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut machine = counter_machine();
+        machine.add_breakpoint(4);
+        machine.add_breakpoint(13);
+
+        for n in 1..=3 {
+            assert_eq!(machine.run().unwrap(), StopStatus::Breakpoint(4));
+            assert_eq!(machine.tape()[14], n);
+        }
+        assert_eq!(machine.run().unwrap(), StopStatus::Breakpoint(13));
+        assert_eq!(machine.run().unwrap(), StopStatus::Halted);
+        assert_eq!(machine.output.borrow().len(), 3);
+
+        assert!(machine.remove_breakpoint(4));
+        assert!(!machine.remove_breakpoint(4));
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut machine = counter_machine();
+        machine.add_watchpoint(15, WatchKind::Read);
+        let hit = WatchHit {
+            pc: 10,
+            addr: 15,
+            access: Access::Read,
+        };
+        assert_eq!(machine.run().unwrap(), StopStatus::Watchpoint(hit));
+        assert_eq!(machine.pc(), 0);
+
+        machine.remove_watchpoint(15);
+        machine.add_watchpoint(14, WatchKind::Write);
+        let hit = WatchHit {
+            pc: 0,
+            addr: 14,
+            access: Access::Write,
+        };
+        assert_eq!(machine.run().unwrap(), StopStatus::Watchpoint(hit));
+        assert_eq!(machine.tape()[14], 2);
+    }
+
+    #[test]
+    fn test_trace() {
+        let trace = Arc::new(Mutex::new(Vec::<TraceEvent>::new()));
+        let mut machine = counter_machine();
+        machine.set_tracer(trace.clone());
+        machine.step().unwrap();
+        machine.step().unwrap();
+        machine.clear_tracer();
+        machine.step().unwrap();
+
+        let trace = trace.lock().unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].loads, [0, 1]);
+        assert_eq!(trace[0].store, Some((14, 1)));
+        assert_eq!(trace[1].loads, [1]);
+        assert_eq!(
+            trace[0].to_string(),
+            "    0: add [14], #1, [14]               ; 0 1 -> [14]=1"
+        );
+    }
+
+    #[test]
+    fn test_instruction_limit() {
+        let tape = assemble("loop: jt #1, #loop").unwrap();
+        let mut machine = IntcodeMachine::new(tape);
+        machine.set_instruction_limit(Some(1000));
+        assert!(matches!(
+            machine.run(),
+            Err(IntcodeError::InstructionLimitExceeded)
+        ));
+        assert_eq!(machine.instruction_count(), 1000);
+
+        // Lowering the limit below what already ran still stops the machine
+        machine.set_instruction_limit(Some(10));
+        assert!(matches!(
+            machine.step(),
+            Err(IntcodeError::InstructionLimitExceeded)
+        ));
+        assert_eq!(machine.instruction_count(), 1000);
+
+        let mut machine = IntcodeMachine::<isize>::new(vec![1101, 1, 1, 5, 99, 0]);
+        for _ in 0..3 {
+            assert_eq!(machine.run().unwrap(), StopStatus::Halted);
+        }
+        assert_eq!(machine.instruction_count(), 2);
+        machine.set_instruction_limit(Some(2));
+        assert_eq!(machine.run().unwrap(), StopStatus::Halted);
+        let mut fork = machine.fork();
+        assert_eq!(fork.run().unwrap(), StopStatus::Halted);
+        assert_eq!(fork.instruction_count(), 2);
+    }
+
+    #[test]
+    fn test_arithmetic_overflow() {
+        let program = format!("1002,5,2,5,99,{}", isize::MAX);
+        let mut machine = IntcodeMachine::new(parse_intcode_program::<isize>(&program));
+        assert!(matches!(
+            machine.run(),
+            Err(IntcodeError::ArithmeticOverflow)
+        ));
+
+        let mut machine = IntcodeMachine::new(parse_intcode_program::<i128>(&program));
+        machine.run_to_completion().unwrap();
+        assert_eq!(machine.tape()[5], 2 * isize::MAX as i128);
+    }
+
+    #[test]
+    fn test_bigint_words() {
+        use num_bigint::BigInt;
+
+        // Squares its input three times, far past the range of i128
+        let tape = parse_intcode_program::<BigInt>(
+            "3,100,2,100,100,100,2,100,100,100,2,100,100,100,4,100,99",
+        );
+        let input = new_stream_ref_from(BigInt::from(1_000_000_007u64));
+        let mut machine = IntcodeMachine::new_io(tape, input, new_stream_ref());
+        machine.run_to_completion().unwrap();
+
+        let expected = BigInt::from(1_000_000_007u64).pow(8);
+        assert_eq!(machine.output.borrow_mut().pop_front(), Some(expected));
+    }
+
+    #[test]
+    fn test_decode_cache_invalidation() {
+        // Patches the operand of its own output instruction on every pass
+        let tape = assemble(
+            "
+            loop:   out #1
+                    add [loop+1], #1, [loop+1]
+                    lt [loop+1], #3, [t]
+                    jt [t], #loop
+                    hlt
+            t:      .data 0
+            ",
+        )
+        .unwrap();
+        for enabled in [true, false] {
+            let mut machine = IntcodeMachine::new(tape.clone());
+            machine.set_decode_cache(enabled);
+            machine.run_to_completion().unwrap();
+            assert_eq!(*machine.output.borrow(), [1, 2]);
+        }
+
+        // Outputs 5 forever, until patched from outside
+        let mut machine = IntcodeMachine::<isize>::new(vec![104, 5, 1105, 1, 0]);
+        machine.set_instruction_limit(Some(2));
+        assert!(machine.run().is_err());
+        machine.write_addr(1, 6).unwrap();
+        machine.set_instruction_limit(Some(4));
+        assert!(machine.run().is_err());
+        assert_eq!(*machine.output.borrow(), [5, 6]);
+    }
+
+    #[test]
+    fn test_decoded_tape() {
+        // Self-modifying code stays correct when started from a shared decode
+        let decoded = DecodedTape::new(
+            assemble(
+                "
+                loop:   out #1
+                        add [loop+1], #1, [loop+1]
+                        lt [loop+1], #3, [t]
+                        jt [t], #loop
+                        hlt
+                t:      .data 0
+                ",
+            )
+            .unwrap(),
+        );
+        for _ in 0..2 {
+            let mut machine: IntcodeMachine =
+                IntcodeMachine::from_decoded(&decoded, new_stream_ref(), new_stream_ref());
+            machine.run_to_completion().unwrap();
+            assert_eq!(*machine.output.borrow(), [1, 2]);
+            assert_eq!(machine.fork().run().unwrap(), StopStatus::Halted);
+        }
+        assert_eq!(decoded.tape()[1], 1);
+
+        let decoded = DecodedTape::new(vec![104, 5, 1105, 1, 0]);
+        let mut machine: IntcodeMachine =
+            IntcodeMachine::from_decoded(&decoded, new_stream_ref(), new_stream_ref());
+        machine.set_instruction_limit(Some(2));
+        assert!(machine.run().is_err());
+        let mut fork = machine.fork();
+        fork.write_addr(1, 6).unwrap();
+        fork.set_instruction_limit(Some(4));
+        assert!(fork.run().is_err());
+        assert_eq!(*fork.output.borrow(), [5, 6]);
+    }
 }
@@ -0,0 +1,175 @@
+use super::{DenseMemory, IntcodeMachine, IntcodeResult, Memory, StopStatus, Tape, Word};
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, PartialEq)]
+pub struct AsciiOutput<W = isize> {
+    pub text: String,
+    // A final value outside the ASCII range, which puzzles use for the answer
+    pub answer: Option<W>,
+    pub status: StopStatus,
+}
+
+impl<W> AsciiOutput<W> {
+    pub fn halted(&self) -> bool {
+        self.status == StopStatus::Halted
+    }
+}
+
+fn to_ascii<W: Word>(value: &W) -> Option<char> {
+    match value.to_isize() {
+        Some(n @ 0..=127) => Some(n as u8 as char),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct AsciiIntcode<W = isize, M = DenseMemory<W>> {
+    pub machine: IntcodeMachine<W, M>,
+}
+
+impl<W: Word> AsciiIntcode<W> {
+    pub fn new(tape: Tape<W>) -> Self {
+        Self::from_machine(IntcodeMachine::new(tape))
+    }
+}
+
+impl<W: Word, M: Memory<W>> AsciiIntcode<W, M> {
+    pub fn from_machine(machine: IntcodeMachine<W, M>) -> Self {
+        AsciiIntcode { machine }
+    }
+
+    pub fn into_machine(self) -> IntcodeMachine<W, M> {
+        self.machine
+    }
+
+    pub fn send_text(&mut self, text: &str) {
+        let mut input = self.machine.input.borrow_mut();
+        input.extend(text.bytes().map(|b| W::from_isize(b as isize)));
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.send_text(line);
+        self.send_text("\n");
+    }
+
+    // Runs until the program waits for input or stops, and decodes what it printed
+    pub fn read_until_prompt(&mut self) -> IntcodeResult<AsciiOutput<W>> {
+        let status = self.machine.run()?;
+        let mut values: Vec<W> = self.machine.output.borrow_mut().drain(..).collect();
+
+        let answer = match values.last() {
+            Some(last) if to_ascii(last).is_none() => values.pop(),
+            _ => None,
+        };
+        let text = values
+            .iter()
+            .map(|v| to_ascii(v).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+
+        Ok(AsciiOutput {
+            text,
+            answer,
+            status,
+        })
+    }
+
+    pub fn run_line(&mut self, line: &str) -> IntcodeResult<AsciiOutput<W>> {
+        self.send_line(line);
+        self.read_until_prompt()
+    }
+
+    // Relays program output to `output` and lines from `input` to the program
+    // until it halts or `input` runs dry
+    pub fn interact(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> io::Result<StopStatus> {
+        loop {
+            let response = self
+                .read_until_prompt()
+                .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+            write!(output, "{}", response.text)?;
+            if let Some(answer) = &response.answer {
+                writeln!(output, "{}", answer)?;
+            }
+            output.flush()?;
+
+            if response.status != StopStatus::BlockedOnInput {
+                return Ok(response.status);
+            }
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(response.status);
+            }
+            self.send_line(line.trim_end_matches(['\r', '\n']));
+        }
+    }
+
+    pub fn interactive(&mut self) -> io::Result<StopStatus> {
+        self.interact(io::stdin().lock(), io::stdout().lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assemble;
+    use super::*;
+
+    // Prompts with "> ", counts the characters of one line, then prints
+    // "ok" and the count plus 1000
+    fn line_counter() -> AsciiIntcode {
+        let tape = assemble(
+            "
+                    out #62
+                    out #32
+            loop:   in [c]
+                    eq [c], #10, [t]
+                    jt [t], #done
+                    add [n], #1, [n]
+                    jt #1, #loop
+            done:   out #111
+                    out #107
+                    out #10
+                    add [n], #1000, [n]
+                    out [n]
+                    hlt
+            c:      .data 0
+            n:      .data 0
+            t:      .data 0
+            ",
+        )
+        .unwrap();
+        AsciiIntcode::new(tape)
+    }
+
+    #[test]
+    fn test_read_until_prompt() {
+        let mut ascii = line_counter();
+        let prompt = ascii.read_until_prompt().unwrap();
+        assert_eq!(prompt.text, "> ");
+        assert_eq!(prompt.answer, None);
+        assert_eq!(prompt.status, StopStatus::BlockedOnInput);
+
+        let response = ascii.run_line("abc").unwrap();
+        assert_eq!(response.text, "ok\n");
+        assert_eq!(response.answer, Some(1003));
+        assert!(response.halted());
+    }
+
+    #[test]
+    fn test_interact() {
+        let mut ascii = line_counter();
+        let mut output = Vec::new();
+        let status = ascii.interact(&b"abcd\r\n"[..], &mut output).unwrap();
+        assert_eq!(status, StopStatus::Halted);
+        assert_eq!(String::from_utf8(output).unwrap(), "> ok\n1004\n");
+
+        let mut ascii = line_counter();
+        let mut output = Vec::new();
+        let status = ascii.interact(&b""[..], &mut output).unwrap();
+        assert_eq!(status, StopStatus::BlockedOnInput);
+        assert_eq!(output, b"> ");
+    }
+}
@@ -0,0 +1,437 @@
+use super::{AddressingMode, Operation, Tape};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    WrongOperandCount(usize),
+    InvalidOperand(String),
+    InvalidLabel(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+    AddressMismatch(usize),
+    InvalidString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+pub type AsmResult<T> = Result<T, AsmError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(isize),
+    Label(String, isize),
+}
+
+#[derive(Debug, Clone)]
+struct AsmOperand {
+    mode: AddressingMode,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Op(Operation, Vec<AsmOperand>),
+    Data(Vec<Expr>),
+}
+
+impl Item {
+    fn word_count(&self) -> usize {
+        match self {
+            Item::Op(_, operands) => 1 + operands.len(),
+            Item::Data(words) => words.len(),
+        }
+    }
+}
+
+fn parse_mnemonic(s: &str) -> Option<Operation> {
+    let operation = match s {
+        "add" => Operation::Add,
+        "mul" => Operation::Multiply,
+        "in" => Operation::Input,
+        "out" => Operation::Output,
+        "jt" => Operation::JumpTrue,
+        "jf" => Operation::JumpFalse,
+        "lt" => Operation::LessThan,
+        "eq" => Operation::Equals,
+        "arb" => Operation::AdjustBasePointer,
+        "hlt" => Operation::Halt,
+        _ => return None,
+    };
+    Some(operation)
+}
+
+fn opcode_number(operation: Operation) -> isize {
+    match operation {
+        Operation::Add => 1,
+        Operation::Multiply => 2,
+        Operation::Input => 3,
+        Operation::Output => 4,
+        Operation::JumpTrue => 5,
+        Operation::JumpFalse => 6,
+        Operation::LessThan => 7,
+        Operation::Equals => 8,
+        Operation::AdjustBasePointer => 9,
+        Operation::Halt => 99,
+    }
+}
+
+fn mode_number(mode: AddressingMode) -> isize {
+    match mode {
+        AddressingMode::AbsoluteAddress => 0,
+        AddressingMode::Immediate => 1,
+        AddressingMode::BasePointerRelative => 2,
+    }
+}
+
+pub fn encode_opcode(operation: Operation, modes: &[AddressingMode]) -> isize {
+    let mut opcode = opcode_number(operation);
+    let mut scale = 100;
+    for &mode in modes {
+        opcode += mode_number(mode) * scale;
+        scale *= 10;
+    }
+    opcode
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_expr(s: &str) -> Option<Expr> {
+    let s = s.trim();
+    if let Ok(n) = s.parse() {
+        return Some(Expr::Number(n));
+    }
+
+    let split = s.get(1..)?.find(['+', '-']).map(|i| i + 1);
+    let (label, offset) = match split {
+        Some(i) => (s[..i].trim(), s[i..].replace(' ', "").parse().ok()?),
+        None => (s, 0),
+    };
+    if !is_label(label) {
+        return None;
+    }
+    Some(Expr::Label(label.to_string(), offset))
+}
+
+fn parse_operand(s: &str) -> Option<AsmOperand> {
+    let s = s.trim();
+    if let Some(imm) = s.strip_prefix('#') {
+        return Some(AsmOperand {
+            mode: AddressingMode::Immediate,
+            expr: parse_expr(imm)?,
+        });
+    }
+
+    let inner = s.strip_prefix('[')?.strip_suffix(']')?.trim();
+    if let Some(rest) = inner.strip_prefix("bp") {
+        let rest = rest.trim();
+        let expr = match rest.chars().next() {
+            None => Expr::Number(0),
+            Some('+') => parse_expr(&rest[1..])?,
+            Some('-') => match parse_expr(&rest[1..])? {
+                Expr::Number(n) => Expr::Number(-n),
+                Expr::Label(..) => return None,
+            },
+            _ => return None,
+        };
+        return Some(AsmOperand {
+            mode: AddressingMode::BasePointerRelative,
+            expr,
+        });
+    }
+
+    Some(AsmOperand {
+        mode: AddressingMode::AbsoluteAddress,
+        expr: parse_expr(inner)?,
+    })
+}
+
+fn parse_string(s: &str) -> Option<Vec<Expr>> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut words = Vec::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '0' => '\0',
+                '\\' => '\\',
+                '"' => '"',
+                _ => return None,
+            },
+            '"' => return None,
+            c => c,
+        };
+        words.push(Expr::Number(c as isize));
+    }
+    Some(words)
+}
+
+fn split_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() || !parts.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+fn parse_item(body: &str) -> Result<Option<Item>, AsmErrorKind> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Ok(None);
+    }
+
+    let (mnemonic, rest) = match body.find(char::is_whitespace) {
+        Some(i) => (&body[..i], &body[i..]),
+        None => (body, ""),
+    };
+    let args = split_operands(rest);
+
+    if mnemonic == ".data" {
+        let mut words = Vec::new();
+        for arg in args {
+            if arg.starts_with('"') {
+                words.extend(parse_string(arg).ok_or(AsmErrorKind::InvalidString)?);
+            } else {
+                let expr =
+                    parse_expr(arg).ok_or_else(|| AsmErrorKind::InvalidOperand(arg.into()))?;
+                words.push(expr);
+            }
+        }
+        return Ok(Some(Item::Data(words)));
+    }
+
+    let operation =
+        parse_mnemonic(mnemonic).ok_or_else(|| AsmErrorKind::UnknownMnemonic(mnemonic.into()))?;
+    if args.len() != operation.operand_count() {
+        return Err(AsmErrorKind::WrongOperandCount(args.len()));
+    }
+
+    let operands = args
+        .iter()
+        .map(|arg| parse_operand(arg).ok_or_else(|| AsmErrorKind::InvalidOperand(arg.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(Item::Op(operation, operands)))
+}
+
+fn resolve(expr: &Expr, labels: &HashMap<String, usize>) -> Result<isize, AsmErrorKind> {
+    match expr {
+        Expr::Number(n) => Ok(*n),
+        Expr::Label(label, offset) => labels
+            .get(label)
+            .map(|&addr| addr as isize + offset)
+            .ok_or_else(|| AsmErrorKind::UndefinedLabel(label.clone())),
+    }
+}
+
+pub fn assemble(source: &str) -> AsmResult<Tape> {
+    let mut items = Vec::<(usize, Item)>::new();
+    let mut labels = HashMap::<String, usize>::new();
+    let mut addr = 0usize;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let err = |kind| AsmError {
+            line: line_no,
+            kind,
+        };
+        let mut body = split_comment(line).trim();
+
+        while let Some(colon) = body.find(':') {
+            let label = body[..colon].trim();
+            if label.starts_with('"') || label.contains(char::is_whitespace) {
+                break;
+            }
+
+            if let Ok(listed_addr) = label.parse::<usize>() {
+                if listed_addr != addr {
+                    return Err(err(AsmErrorKind::AddressMismatch(listed_addr)));
+                }
+            } else if !is_label(label) {
+                return Err(err(AsmErrorKind::InvalidLabel(label.to_string())));
+            } else if labels.insert(label.to_string(), addr).is_some() {
+                return Err(err(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            body = body[colon + 1..].trim();
+        }
+
+        if let Some(item) = parse_item(body).map_err(err)? {
+            addr += item.word_count();
+            items.push((line_no, item));
+        }
+    }
+
+    let mut tape = Tape::with_capacity(addr);
+    for (line, item) in items {
+        let err = |kind| AsmError { line, kind };
+        match item {
+            Item::Op(operation, operands) => {
+                let modes: Vec<_> = operands.iter().map(|o| o.mode).collect();
+                tape.push(encode_opcode(operation, &modes));
+                for operand in operands {
+                    tape.push(resolve(&operand.expr, &labels).map_err(err)?);
+                }
+            }
+            Item::Data(words) => {
+                for word in words {
+                    tape.push(resolve(&word, &labels).map_err(err)?);
+                }
+            }
+        }
+    }
+
+    Ok(tape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::disasm::listing;
+    use super::super::{new_stream_ref, new_stream_ref_from_iter, IntcodeMachine};
+    use super::*;
+
+    #[test]
+    fn test_encode_opcode() {
+        use AddressingMode::*;
+        assert_eq!(encode_opcode(Operation::Halt, &[]), 99);
+        assert_eq!(
+            encode_opcode(
+                Operation::Multiply,
+                &[AbsoluteAddress, Immediate, AbsoluteAddress]
+            ),
+            1002
+        );
+        assert_eq!(
+            encode_opcode(
+                Operation::LessThan,
+                &[Immediate, Immediate, BasePointerRelative]
+            ),
+            21107
+        );
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = r#"
+            ; Sum numbers from input until a zero is read
+            loop:   in [x]
+                    jf [x], #done
+                    add [sum], [x], [sum]
+                    jt #1, #loop
+            done:   out [sum]
+                    hlt
+            x:      .data 0
+            sum:    .data 0
+        "#;
+        let tape = assemble(source).unwrap();
+        assert_eq!(
+            tape,
+            [3, 15, 1006, 15, 12, 1, 16, 15, 16, 1105, 1, 0, 4, 16, 99, 0, 0]
+        );
+
+        let input = new_stream_ref_from_iter([3, 4, 5, 0]);
+        let mut machine = IntcodeMachine::new_io(tape, input, new_stream_ref());
+        machine.run_to_completion().unwrap();
+        assert_eq!(machine.output.borrow_mut().pop_front(), Some(12));
+    }
+
+    #[test]
+    fn test_strings_and_bp() {
+        let source = r#"
+                    arb #msg
+            next:   jf [bp], #end       ; stop at the terminating zero
+                    out [bp+0]
+                    arb #1
+                    jt #1, #next
+            end:    hlt
+            msg:    .data "Hi, \"you\";\n", 0
+        "#;
+        let tape = assemble(source).unwrap();
+        let mut machine = IntcodeMachine::new(tape);
+        machine.run_to_completion().unwrap();
+        let output: String = machine
+            .output
+            .borrow_mut()
+            .drain(..)
+            .map(|c| c as u8 as char)
+            .collect();
+        assert_eq!(output, "Hi, \"you\";\n");
+    }
+
+    #[test]
+    fn test_listing_roundtrip() {
+        let tape: Tape = vec![1002, 4, 3, 4, 33, 109, -7, 21107, 1, 2, 3, 99, -1, 204, -2];
+        assert_eq!(assemble(&listing(&tape)).unwrap(), tape);
+    }
+
+    #[test]
+    fn test_label_offsets() {
+        let tape = assemble("add [a+1], #a-1, [bp-2]\na: .data 7, 8").unwrap();
+        assert_eq!(tape, [21001, 5, 3, -2, 7, 8]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let kind = |source| assemble(source).unwrap_err().kind;
+        assert_eq!(kind("nop"), AsmErrorKind::UnknownMnemonic("nop".into()));
+        assert_eq!(kind("add [1], [2]"), AsmErrorKind::WrongOperandCount(2));
+        assert_eq!(kind("out {1}"), AsmErrorKind::InvalidOperand("{1}".into()));
+        assert_eq!(kind("out [x]"), AsmErrorKind::UndefinedLabel("x".into()));
+        assert_eq!(
+            kind("a: hlt\na: hlt"),
+            AsmErrorKind::DuplicateLabel("a".into())
+        );
+        assert_eq!(kind("3: hlt"), AsmErrorKind::AddressMismatch(3));
+        assert_eq!(kind(".data \"\\q\""), AsmErrorKind::InvalidString);
+        assert_eq!(assemble("hlt\nout [x]").unwrap_err().line, 2);
+    }
+}
@@ -0,0 +1,181 @@
+use super::disasm::decode_at;
+use super::{Opcode, Tape, Word};
+use std::sync::Arc;
+
+// Instructions are at most this many words long
+const MAX_INSTRUCTION_WORDS: usize = 4;
+
+// Code beyond this is decoded on every visit rather than cached
+const MAX_CACHED_ADDRESS: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+enum Entry<W> {
+    Empty,
+    // Run once already; code that only ever runs once is not worth caching
+    Seen,
+    Decoded(Arc<Opcode<W>>),
+}
+
+type Decoded<W> = Arc<[Option<Arc<Opcode<W>>>]>;
+
+// A tape decoded once at every address, for starting many machines on the same
+// program without each of them decoding it again
+#[derive(Debug, Clone)]
+pub struct DecodedTape<W = isize> {
+    tape: Arc<Tape<W>>,
+    opcodes: Decoded<W>,
+}
+
+impl<W: Word> DecodedTape<W> {
+    pub fn new(tape: Tape<W>) -> Self {
+        let opcodes = (0..tape.len().min(MAX_CACHED_ADDRESS))
+            .map(|addr| decode_at(&tape, addr).map(Arc::new))
+            .collect();
+        DecodedTape {
+            tape: Arc::new(tape),
+            opcodes,
+        }
+    }
+
+    pub fn tape(&self) -> &Tape<W> {
+        &self.tape
+    }
+}
+
+// Decoded instructions by address; any write to a word an entry was decoded
+// from drops that entry. Entries left Empty fall back to the shared tape.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeCache<W> {
+    enabled: bool,
+    entries: Vec<Entry<W>>,
+    shared: Option<Decoded<W>>,
+}
+
+impl<W> DecodeCache<W> {
+    pub fn new() -> Self {
+        DecodeCache {
+            enabled: true,
+            entries: Vec::new(),
+            shared: None,
+        }
+    }
+
+    pub fn from_decoded(decoded: &DecodedTape<W>) -> Self {
+        DecodeCache {
+            shared: Some(decoded.opcodes.clone()),
+            ..Self::new()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.clear();
+    }
+
+    pub fn get(&self, addr: usize) -> Option<Arc<Opcode<W>>> {
+        match self.entries.get(addr) {
+            Some(Entry::Decoded(opcode)) => Some(opcode.clone()),
+            Some(Entry::Seen) => None,
+            _ => self.shared.as_ref()?.get(addr)?.clone(),
+        }
+    }
+
+    // Returns whether the instruction at `addr` should be cached once decoded
+    pub fn visit(&mut self, addr: usize) -> bool {
+        if !self.enabled || addr >= MAX_CACHED_ADDRESS {
+            return false;
+        }
+        if addr >= self.entries.len() {
+            self.entries.resize_with(addr + 1, || Entry::Empty);
+        }
+        match self.entries[addr] {
+            Entry::Empty => {
+                self.entries[addr] = Entry::Seen;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    pub fn insert(&mut self, addr: usize, opcode: Arc<Opcode<W>>) {
+        self.entries[addr] = Entry::Decoded(opcode);
+    }
+
+    pub fn invalidate(&mut self, addr: usize) {
+        let start = addr.saturating_sub(MAX_INSTRUCTION_WORDS - 1);
+        let shared = self.shared.as_ref().map_or(0, |s| s.len());
+        let end = self.entries.len().max(shared).min(addr + 1);
+        for start in start..end {
+            let covers = match self.get(start) {
+                Some(opcode) => addr <= start + opcode.operands.len(),
+                None => false,
+            };
+            if covers {
+                if start >= self.entries.len() {
+                    self.entries.resize_with(start + 1, || Entry::Empty);
+                }
+                self.entries[start] = Entry::Seen;
+            }
+        }
+    }
+
+    // The shared tape no longer matches memory once it is replaced wholesale
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.shared = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{AddressingMode, Operand, Operation};
+    use super::*;
+
+    #[test]
+    fn test_invalidate() {
+        let operand = Operand {
+            mode: AddressingMode::Immediate,
+            value: 0,
+        };
+        let opcode = Arc::new(Opcode {
+            operation: Operation::Add,
+            operands: vec![operand; 3],
+        });
+
+        let mut cache = DecodeCache::new();
+        assert!(!cache.visit(10));
+        assert!(cache.visit(10));
+        cache.insert(10, opcode);
+        cache.invalidate(14);
+        cache.invalidate(9);
+        assert!(cache.get(10).is_some());
+        cache.invalidate(13);
+        assert!(cache.get(10).is_none());
+        assert!(cache.visit(10));
+
+        cache.set_enabled(false);
+        assert!(!cache.visit(10));
+    }
+
+    #[test]
+    fn test_shared() {
+        let decoded = DecodedTape::<isize>::new(vec![1101, 1, 2, 7, 99, 42]);
+        let mut cache = DecodeCache::from_decoded(&decoded);
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(4).is_some());
+        assert!(cache.get(5).is_none());
+
+        let mut other = cache.clone();
+        other.invalidate(3);
+        assert!(other.get(0).is_none());
+        assert!(other.get(4).is_some());
+        assert!(cache.get(0).is_some());
+
+        cache.clear();
+        assert!(cache.get(4).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use super::disasm::{decode_at, Instruction, InstructionKind};
+use super::{AddressingMode, Opcode, Operation, Tape, Word};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edge {
+    Fallthrough,
+    Taken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<W = isize> {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: Vec<Instruction<W>>,
+    pub successors: Vec<(usize, Edge)>,
+    // Ends in a jump whose target is only known at run time
+    pub dynamic: bool,
+    // Runs into words that do not decode as an instruction
+    pub invalid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfModification {
+    pub pc: usize,
+    pub target: usize,
+    pub instruction: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph<W = isize> {
+    pub entry: usize,
+    pub blocks: BTreeMap<usize, Block<W>>,
+    pub self_modifications: Vec<SelfModification>,
+}
+
+fn immediate<W: Word>(opcode: &Opcode<W>, i: usize) -> Option<&W> {
+    let operand = &opcode.operands[i];
+    match operand.mode {
+        AddressingMode::Immediate => Some(&operand.value),
+        _ => None,
+    }
+}
+
+fn to_addr<W: Word>(value: &W) -> Option<usize> {
+    usize::try_from(value.to_isize()?).ok()
+}
+
+// Where control can go after the instruction at `addr`, and whether a jump
+// target could not be resolved statically
+fn successors<W: Word>(addr: usize, opcode: &Opcode<W>) -> (Vec<(usize, Edge)>, bool) {
+    let next = addr + 1 + opcode.operands.len();
+    let jump_if = match opcode.operation {
+        Operation::Halt => return (Vec::new(), false),
+        Operation::JumpTrue => true,
+        Operation::JumpFalse => false,
+        _ => return (vec![(next, Edge::Fallthrough)], false),
+    };
+
+    // An immediate condition makes the jump unconditional, or a no-op
+    let (taken, falls) = match immediate(opcode, 0) {
+        Some(cond) if cond.is_zero() != jump_if => (true, false),
+        Some(_) => (false, true),
+        None => (true, true),
+    };
+
+    let mut edges = Vec::new();
+    let mut dynamic = false;
+    if taken {
+        match immediate(opcode, 1).and_then(to_addr) {
+            Some(target) => edges.push((target, Edge::Taken)),
+            None => dynamic = true,
+        }
+    }
+    if falls {
+        edges.push((next, Edge::Fallthrough));
+    }
+    (edges, dynamic)
+}
+
+fn ends_block<W>(opcode: &Opcode<W>) -> bool {
+    matches!(
+        opcode.operation,
+        Operation::JumpTrue | Operation::JumpFalse | Operation::Halt
+    )
+}
+
+fn store_target<W: Word>(opcode: &Opcode<W>) -> Option<usize> {
+    let store = match opcode.operation {
+        Operation::Add | Operation::Multiply | Operation::LessThan | Operation::Equals => 2,
+        Operation::Input => 0,
+        _ => return None,
+    };
+    let operand = &opcode.operands[store];
+    match operand.mode {
+        AddressingMode::AbsoluteAddress => to_addr(&operand.value),
+        _ => None,
+    }
+}
+
+impl<W: Word> ControlFlowGraph<W> {
+    // Decodes everything reachable from `entry` through immediate jump targets
+    pub fn build(tape: &Tape<W>, entry: usize) -> Self {
+        let mut code = BTreeMap::<usize, Opcode<W>>::new();
+        let mut leaders = BTreeSet::from([entry]);
+        let mut pending = vec![entry];
+
+        while let Some(addr) = pending.pop() {
+            if code.contains_key(&addr) {
+                continue;
+            }
+            let opcode = match decode_at(tape, addr) {
+                Some(opcode) => opcode,
+                None => continue,
+            };
+
+            let (edges, _) = successors(addr, &opcode);
+            for &(target, edge) in edges.iter() {
+                if edge == Edge::Taken || ends_block(&opcode) {
+                    leaders.insert(target);
+                }
+                pending.push(target);
+            }
+            code.insert(addr, opcode);
+        }
+
+        let mut blocks = BTreeMap::new();
+        for &start in leaders.iter() {
+            let mut block = Block {
+                start,
+                end: start,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+                dynamic: false,
+                invalid: false,
+            };
+
+            loop {
+                let opcode = match code.get(&block.end) {
+                    Some(opcode) => opcode,
+                    None => {
+                        block.invalid = true;
+                        break;
+                    }
+                };
+                let addr = block.end;
+                block.end = addr + 1 + opcode.operands.len();
+                block.instructions.push(Instruction {
+                    addr,
+                    kind: InstructionKind::Op(opcode.clone()),
+                });
+
+                let (edges, dynamic) = successors(addr, opcode);
+                if ends_block(opcode) || leaders.contains(&block.end) {
+                    block.successors = edges;
+                    block.dynamic = dynamic;
+                    break;
+                }
+            }
+            blocks.insert(start, block);
+        }
+
+        let mut self_modifications = Vec::new();
+        for (&pc, opcode) in code.iter() {
+            let target = match store_target(opcode) {
+                Some(target) => target,
+                None => continue,
+            };
+            let instruction = code.range(..=target).next_back();
+            if let Some((&instruction, modified)) = instruction {
+                if target < instruction + 1 + modified.operands.len() {
+                    self_modifications.push(SelfModification {
+                        pc,
+                        target,
+                        instruction,
+                    });
+                }
+            }
+        }
+
+        ControlFlowGraph {
+            entry,
+            blocks,
+            self_modifications,
+        }
+    }
+
+    pub fn is_modified(&self, block: &Block<W>) -> bool {
+        self.self_modifications
+            .iter()
+            .any(|m| (block.start..block.end).contains(&m.target))
+    }
+
+    // Self-modified blocks are drawn in red and dynamic jumps lead to "?"
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for instruction in block.instructions.iter() {
+                write!(label, "{}\\l", instruction.to_string().trim_start()).unwrap();
+            }
+            if block.invalid {
+                write!(label, "{}: <invalid>\\l", block.end).unwrap();
+            }
+            let color = if self.is_modified(block) {
+                ", color=red"
+            } else {
+                ""
+            };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, color).unwrap();
+
+            for &(target, edge) in block.successors.iter() {
+                let style = match edge {
+                    Edge::Taken => "",
+                    Edge::Fallthrough => " [style=dashed]",
+                };
+                writeln!(dot, "    b{} -> b{}{};", block.start, target, style).unwrap();
+            }
+            if block.dynamic {
+                writeln!(dot, "    b{} -> dynamic;", block.start).unwrap();
+            }
+        }
+
+        if self.blocks.values().any(|b| b.dynamic) {
+            writeln!(dot, "    dynamic [shape=circle, label=\"?\"];").unwrap();
+        }
+        for m in self.self_modifications.iter() {
+            writeln!(
+                dot,
+                "    // {} writes [{}] inside the instruction at {}",
+                m.pc, m.target, m.instruction
+            )
+            .unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assemble;
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let tape = assemble(
+            "
+            loop:   add [n], #1, [n]
+                    out [n]
+                    lt [n], #3, [flag]
+                    jt [flag], #loop
+                    jf #0, #done
+                    out #-1
+            done:   hlt
+            n:      .data 0
+            flag:   .data 0
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::build(&tape, 0);
+
+        let blocks: Vec<_> = cfg
+            .blocks
+            .values()
+            .map(|b| (b.start, b.end, b.successors.clone()))
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                (0, 13, vec![(0, Edge::Taken), (13, Edge::Fallthrough)]),
+                (13, 16, vec![(18, Edge::Taken)]),
+                (18, 19, vec![]),
+            ]
+        );
+        // The output after the unconditional jump is never decoded
+        assert!(cfg.self_modifications.is_empty());
+        assert!(cfg.blocks.values().all(|b| !b.dynamic && !b.invalid));
+    }
+
+    #[test]
+    fn test_self_modification() {
+        let tape = assemble(
+            "
+                    add #7, #0, [target+1]
+                    in [x]
+            target: out #0
+                    jt #1, [x]
+            x:      .data 0
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::build(&tape, 0);
+
+        assert_eq!(
+            cfg.self_modifications,
+            [SelfModification {
+                pc: 0,
+                target: 7,
+                instruction: 6,
+            }]
+        );
+        let block = &cfg.blocks[&0];
+        assert!(block.dynamic);
+        assert!(block.successors.is_empty());
+        assert!(cfg.is_modified(block));
+
+        let dot = cfg.to_dot();
+        assert!(dot.contains("b0 [label=\"0: add #7, #0, [7]\\l4: in [11]\\l6: out #0\\l8: jt #1, [11]\\l\", color=red];"));
+        assert!(dot.contains("b0 -> dynamic;"));
+        assert!(dot.contains("// 0 writes [7] inside the instruction at 6"));
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let tape: Tape = vec![1105, 1, 4, 99, 42];
+        let cfg = ControlFlowGraph::build(&tape, 0);
+        assert_eq!(cfg.blocks.len(), 2);
+        assert!(cfg.blocks[&4].invalid);
+        assert!(cfg.to_dot().contains("b4 [label=\"4: <invalid>\\l\"];"));
+    }
+}
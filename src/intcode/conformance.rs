@@ -0,0 +1,227 @@
+// Behaviour every Intcode interpreter change must preserve: each operation and
+// addressing mode, every error, and the published 2019 puzzle examples
+use super::*;
+use std::cmp::Ordering;
+
+// Runs with and without the decode cache, which must always agree
+fn run_with(program: &str, input: &[isize]) -> IntcodeResult<(Tape, Vec<isize>)> {
+    let tape: Tape = parse_intcode_program(program);
+    let mut results = Vec::new();
+
+    for cached in [true, false] {
+        let input = new_stream_ref_from_iter(input.iter().copied());
+        let mut machine = IntcodeMachine::new_io(tape.clone(), input, new_stream_ref());
+        machine.set_decode_cache(cached);
+        machine.set_instruction_limit(Some(100_000));
+        let result = machine.run_to_completion().map(|_| {
+            let output = machine.output.borrow().iter().copied().collect();
+            (machine.tape().clone(), output)
+        });
+        results.push(result);
+    }
+
+    let uncached = results.pop().unwrap();
+    let cached = results.pop().unwrap();
+    match (&cached, &uncached) {
+        (Ok(a), Ok(b)) => assert_eq!(a, b),
+        (Err(a), Err(b)) => assert_eq!(format!("{:?}", a), format!("{:?}", b)),
+        _ => panic!("Cache changed the outcome: {:?} vs {:?}", cached, uncached),
+    }
+    cached
+}
+
+fn memory_after(program: &str) -> Tape {
+    run_with(program, &[]).unwrap().0
+}
+
+fn outputs(program: &str, input: &[isize]) -> Vec<isize> {
+    run_with(program, input).unwrap().1
+}
+
+fn error(program: &str, input: &[isize]) -> IntcodeError {
+    run_with(program, input).unwrap_err()
+}
+
+#[test]
+fn test_operations() {
+    assert_eq!(memory_after("1101,2,3,0,99"), [5, 2, 3, 0, 99]);
+    assert_eq!(memory_after("1102,2,3,0,99"), [6, 2, 3, 0, 99]);
+    assert_eq!(outputs("3,0,4,0,99", &[-7]), [-7]);
+    assert_eq!(outputs("104,42,99", &[]), [42]);
+
+    // Jumps skip the "out #0" when taken
+    assert_eq!(outputs("1105,1,5,104,0,104,1,99", &[]), [1]);
+    assert_eq!(outputs("1105,0,5,104,0,104,1,99", &[]), [0, 1]);
+    assert_eq!(outputs("1106,0,5,104,0,104,1,99", &[]), [1]);
+    assert_eq!(outputs("1106,1,5,104,0,104,1,99", &[]), [0, 1]);
+
+    assert_eq!(memory_after("1107,1,2,0,99"), [1, 1, 2, 0, 99]);
+    assert_eq!(memory_after("1107,2,2,0,99"), [0, 2, 2, 0, 99]);
+    assert_eq!(memory_after("1108,2,2,0,99"), [1, 2, 2, 0, 99]);
+    assert_eq!(memory_after("1108,2,3,0,99"), [0, 2, 3, 0, 99]);
+
+    // arb moves the base, then a relative output reads relative to it
+    assert_eq!(outputs("109,5,204,-1,99", &[]), [99]);
+    assert_eq!(outputs("109,5,109,-3,204,0,99", &[]), [109]);
+
+    let mut machine = IntcodeMachine::<isize>::new(vec![99, 1, 2]);
+    assert_eq!(machine.run().unwrap(), StopStatus::Halted);
+    assert_eq!(machine.pc(), 0);
+}
+
+#[test]
+fn test_addressing_modes() {
+    // Absolute loads and store
+    assert_eq!(
+        memory_after("1,5,6,7,99,20,22,0"),
+        [1, 5, 6, 7, 99, 20, 22, 42]
+    );
+    // Immediate loads
+    assert_eq!(memory_after("1101,20,22,5,99,0"), [1101, 20, 22, 5, 99, 42]);
+    // Relative loads and store, with a negative offset
+    assert_eq!(
+        memory_after("109,10,22201,-2,-1,0,99,0,20,22,0"),
+        [109, 10, 22201, -2, -1, 0, 99, 0, 20, 22, 42]
+    );
+    // Mixed modes: [bp+0] + #1 -> [0]
+    assert_eq!(
+        memory_after("109,6,1201,0,1,0,99"),
+        [100, 6, 1201, 0, 1, 0, 99]
+    );
+    assert_eq!(outputs("109,3,203,0,204,0,99", &[8]), [8]);
+
+    // Writes past the end of the program grow memory, reads there see zero
+    assert_eq!(memory_after("1101,1,1,7,99"), [1101, 1, 1, 7, 99, 0, 0, 2]);
+    assert_eq!(outputs("4,100,99", &[]), [0]);
+}
+
+#[test]
+fn test_immediate_store() {
+    for program in [
+        "11101,1,1,0,99",
+        "11102,1,1,0,99",
+        "11107,1,1,0,99",
+        "11108,1,1,0,99",
+    ] {
+        assert!(matches!(
+            error(program, &[]),
+            IntcodeError::InvalidStoreAddressingMode
+        ));
+    }
+    assert!(matches!(
+        error("103,0,99", &[1]),
+        IntcodeError::InvalidStoreAddressingMode
+    ));
+}
+
+#[test]
+fn test_errors() {
+    assert!(matches!(
+        error("42,99", &[]),
+        IntcodeError::InvalidOpcodeOperation
+    ));
+    assert!(matches!(error("-1,99", &[]), IntcodeError::NegativeOpcode));
+    assert!(matches!(
+        error("301,0,0,0,99", &[]),
+        IntcodeError::InvalidAddressingMode
+    ));
+    assert!(matches!(
+        error("100001,0,0,0,99", &[]),
+        IntcodeError::InvalidAddressingMode
+    ));
+    assert!(matches!(
+        error("4,-1,99", &[]),
+        IntcodeError::NegativeAddress
+    ));
+    assert!(matches!(
+        error("109,-1,204,0,99", &[]),
+        IntcodeError::NegativeAddress
+    ));
+    assert!(matches!(
+        error("1105,1,-5", &[]),
+        IntcodeError::NegativeAddress
+    ));
+    assert!(matches!(
+        error("3,0,99", &[]),
+        IntcodeError::DidNotRunToCompletion
+    ));
+    assert!(matches!(
+        error("1105,1,0", &[]),
+        IntcodeError::InstructionLimitExceeded
+    ));
+    assert!(matches!(
+        error("1102,9223372036854775807,2,0,99", &[]),
+        IntcodeError::ArithmeticOverflow
+    ));
+    assert!(matches!(
+        error("1101,-9223372036854775807,-2,0,99", &[]),
+        IntcodeError::ArithmeticOverflow
+    ));
+    assert!(matches!(
+        error("4,100000000,99", &[]),
+        IntcodeError::AddressOutOfRange
+    ));
+    assert!(matches!(
+        error("1101,0,0,100000000,99", &[]),
+        IntcodeError::AddressOutOfRange
+    ));
+}
+
+#[test]
+fn test_day2_examples() {
+    assert_eq!(memory_after("1,0,0,0,99"), [2, 0, 0, 0, 99]);
+    assert_eq!(memory_after("2,3,0,3,99"), [2, 3, 0, 6, 99]);
+    assert_eq!(memory_after("2,4,4,5,99,0"), [2, 4, 4, 5, 99, 9801]);
+    assert_eq!(
+        memory_after("1,1,1,4,99,5,6,0,99"),
+        [30, 1, 1, 4, 2, 5, 6, 0, 99]
+    );
+    assert_eq!(
+        memory_after("1,9,10,3,2,3,11,0,99,30,40,50"),
+        [3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
+    );
+}
+
+#[test]
+fn test_day5_examples() {
+    assert_eq!(outputs("3,0,4,0,99", &[1234]), [1234]);
+    assert_eq!(memory_after("1002,4,3,4,33"), [1002, 4, 3, 4, 99]);
+    assert_eq!(memory_after("1101,100,-1,4,0"), [1101, 100, -1, 4, 99]);
+
+    let compare_to_8 = [
+        ("3,9,8,9,10,9,4,9,99,-1,8", Ordering::Equal),
+        ("3,9,7,9,10,9,4,9,99,-1,8", Ordering::Less),
+        ("3,3,1108,-1,8,3,4,3,99", Ordering::Equal),
+        ("3,3,1107,-1,8,3,4,3,99", Ordering::Less),
+    ];
+    let is_nonzero = [
+        "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+    ];
+    let around_8 = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                    1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                    999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+    for n in [-3, 0, 7, 8, 9, 100] {
+        for (program, expected) in compare_to_8.iter() {
+            assert_eq!(outputs(program, &[n]), [(n.cmp(&8) == *expected) as isize]);
+        }
+        for program in is_nonzero.iter() {
+            assert_eq!(outputs(program, &[n]), [(n != 0) as isize]);
+        }
+        let expected = 999 + (n.cmp(&8) as isize + 1);
+        assert_eq!(outputs(around_8, &[n]), [expected]);
+    }
+}
+
+#[test]
+fn test_day9_examples() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    assert_eq!(outputs(quine, &[]), parse_intcode_program::<isize>(quine));
+
+    let product = outputs("1102,34915192,34915192,7,4,7,99,0", &[]);
+    assert_eq!(product, [1219070632396864]);
+    assert_eq!(product[0].to_string().len(), 16);
+
+    assert_eq!(outputs("104,1125899906842624,99", &[]), [1125899906842624]);
+}
@@ -0,0 +1,154 @@
+use super::{decode_opcode, AddressingMode, Opcode, Operand, Operation, Tape, Word};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstructionKind<W = isize> {
+    Op(Opcode<W>),
+    Data(W),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction<W = isize> {
+    pub addr: usize,
+    pub kind: InstructionKind<W>,
+}
+
+impl<W> Instruction<W> {
+    pub fn word_count(&self) -> usize {
+        match &self.kind {
+            InstructionKind::Op(opcode) => 1 + opcode.operands.len(),
+            InstructionKind::Data(_) => 1,
+        }
+    }
+
+    pub fn next_addr(&self) -> usize {
+        self.addr + self.word_count()
+    }
+}
+
+impl Operation {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Operation::Add => "add",
+            Operation::Multiply => "mul",
+            Operation::Input => "in",
+            Operation::Output => "out",
+            Operation::JumpTrue => "jt",
+            Operation::JumpFalse => "jf",
+            Operation::LessThan => "lt",
+            Operation::Equals => "eq",
+            Operation::AdjustBasePointer => "arb",
+            Operation::Halt => "hlt",
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Operand<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value.to_string();
+        match self.mode {
+            AddressingMode::AbsoluteAddress => write!(f, "[{}]", value),
+            AddressingMode::Immediate => write!(f, "#{}", value),
+            AddressingMode::BasePointerRelative => match value.strip_prefix('-') {
+                Some(magnitude) => write!(f, "[bp-{}]", magnitude),
+                None => write!(f, "[bp+{}]", value),
+            },
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Opcode<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation.mnemonic())?;
+        for (i, operand) in self.operands.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", sep, operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> fmt::Display for Instruction<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>5}: ", self.addr)?;
+        match &self.kind {
+            InstructionKind::Op(opcode) => write!(f, "{}", opcode),
+            InstructionKind::Data(value) => write!(f, ".data {}", value),
+        }
+    }
+}
+
+pub fn decode_at<W: Word>(tape: &[W], addr: usize) -> Option<Opcode<W>> {
+    let (operation, modes) = decode_opcode(tape.get(addr)?.to_isize()?).ok()?;
+    let count = operation.operand_count();
+    let values = tape.get(addr + 1..addr + 1 + count)?;
+
+    let operands = modes
+        .iter()
+        .zip(values)
+        .map(|(&mode, value)| Operand {
+            mode,
+            value: value.clone(),
+        })
+        .collect();
+    Some(Opcode {
+        operation,
+        operands,
+    })
+}
+
+pub fn disassemble<W: Word>(tape: &Tape<W>) -> Vec<Instruction<W>> {
+    let mut instructions = Vec::new();
+    let mut addr = 0;
+
+    while addr < tape.len() {
+        let kind = match decode_at(tape, addr) {
+            Some(opcode) => InstructionKind::Op(opcode),
+            None => InstructionKind::Data(tape[addr].clone()),
+        };
+        let instruction = Instruction { addr, kind };
+        addr = instruction.next_addr();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+pub fn listing<W: Word>(tape: &Tape<W>) -> String {
+    disassemble(tape)
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let tape: Tape = vec![1002, 4, 3, 4, 33, 109, -7, 21107, 1, 2, 3, 99, -1];
+        let lines: Vec<_> = disassemble(&tape).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "    0: mul [4], #3, [4]",
+                "    4: .data 33",
+                "    5: arb #-7",
+                "    7: lt #1, #2, [bp+3]",
+                "   11: hlt",
+                "   12: .data -1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_instruction() {
+        let tape: Tape = vec![204, -2, 1, 0];
+        let lines: Vec<_> = disassemble(&tape).iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            lines,
+            ["    0: out [bp-2]", "    2: .data 1", "    3: .data 0"]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use super::{
+    DenseMemory, IntcodeError, IntcodeMachine, IntcodeResult, Memory, StopStatus, Stream,
+    StreamRef, Tape, Word,
+};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub trait IntcodeIo<W>: Clone + Debug {
+    fn from_stream(values: Stream<W>) -> Self;
+    fn try_read(&self) -> Option<W>;
+    fn write(&self, value: W);
+    fn contents(&self) -> Stream<W>;
+    fn set_contents(&self, values: &Stream<W>);
+}
+
+impl<W: Clone + Debug> IntcodeIo<W> for StreamRef<W> {
+    fn from_stream(values: Stream<W>) -> Self {
+        Rc::new(RefCell::new(values))
+    }
+
+    fn try_read(&self) -> Option<W> {
+        self.borrow_mut().pop_front()
+    }
+
+    fn write(&self, value: W) {
+        self.borrow_mut().push_back(value)
+    }
+
+    fn contents(&self) -> Stream<W> {
+        self.borrow().clone()
+    }
+
+    fn set_contents(&self, values: &Stream<W>) {
+        self.borrow_mut().clone_from(values)
+    }
+}
+
+#[derive(Debug)]
+struct ChannelState<W> {
+    queue: Stream<W>,
+    closed: bool,
+}
+
+#[derive(Debug)]
+pub struct Channel<W = isize> {
+    shared: Arc<(Mutex<ChannelState<W>>, Condvar)>,
+}
+
+impl<W> Clone for Channel<W> {
+    fn clone(&self) -> Self {
+        Channel {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<W> Default for Channel<W> {
+    fn default() -> Self {
+        Self::from_queue(Stream::new())
+    }
+}
+
+impl<W> Channel<W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn from_queue(queue: Stream<W>) -> Self {
+        let state = ChannelState {
+            queue,
+            closed: false,
+        };
+        Channel {
+            shared: Arc::new((Mutex::new(state), Condvar::new())),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ChannelState<W>> {
+        self.shared.0.lock().unwrap()
+    }
+
+    pub fn send(&self, value: W) {
+        self.state().queue.push_back(value);
+        self.shared.1.notify_all();
+    }
+
+    pub fn try_recv(&self) -> Option<W> {
+        self.state().queue.pop_front()
+    }
+
+    // Blocks until a value arrives; None once the channel is closed and drained
+    pub fn recv(&self) -> Option<W> {
+        let mut state = self.state();
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                return Some(value);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.shared.1.wait(state).unwrap();
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<W> {
+        let (mut state, _) = self
+            .shared
+            .1
+            .wait_timeout_while(self.state(), timeout, |s| s.queue.is_empty() && !s.closed)
+            .unwrap();
+        state.queue.pop_front()
+    }
+
+    pub fn wait_readable(&self) -> bool {
+        let state = self
+            .shared
+            .1
+            .wait_while(self.state(), |s| s.queue.is_empty() && !s.closed)
+            .unwrap();
+        !state.queue.is_empty()
+    }
+
+    pub fn close(&self) {
+        self.state().closed = true;
+        self.shared.1.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.state().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state().queue.is_empty()
+    }
+
+    pub fn drain(&self) -> Vec<W> {
+        self.state().queue.drain(..).collect()
+    }
+}
+
+impl<W: Clone + Debug> IntcodeIo<W> for Channel<W> {
+    fn from_stream(values: Stream<W>) -> Self {
+        Self::from_queue(values)
+    }
+
+    fn try_read(&self) -> Option<W> {
+        self.try_recv()
+    }
+
+    fn write(&self, value: W) {
+        self.send(value)
+    }
+
+    fn contents(&self) -> Stream<W> {
+        self.state().queue.clone()
+    }
+
+    fn set_contents(&self, values: &Stream<W>) {
+        self.state().queue.clone_from(values);
+        self.shared.1.notify_all();
+    }
+}
+
+pub type ChannelMachine<W = isize> = IntcodeMachine<W, DenseMemory<W>, Channel<W>>;
+
+impl<W: Word, M: Memory<W>> IntcodeMachine<W, M, Channel<W>> {
+    // Runs until halted, waiting on the input channel whenever it is empty.
+    // The output channel is closed once the machine can make no more progress,
+    // on halt, on error or with its input closed and drained, so downstream
+    // readers terminate. Breakpoints and watchpoints leave it open to resume.
+    pub fn run_blocking(&mut self) -> IntcodeResult<StopStatus> {
+        let result = self.run_until_stopped();
+        match result {
+            Ok(StopStatus::Breakpoint(_)) | Ok(StopStatus::Watchpoint(_)) => (),
+            _ => self.output.close(),
+        }
+        result
+    }
+
+    fn run_until_stopped(&mut self) -> IntcodeResult<StopStatus> {
+        loop {
+            match self.run()? {
+                StopStatus::BlockedOnInput => {
+                    if !self.input.wait_readable() {
+                        return Ok(StopStatus::BlockedOnInput);
+                    }
+                }
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+impl<W: Word + Send + Sync + 'static, M: Memory<W> + Send + 'static>
+    IntcodeMachine<W, M, Channel<W>>
+{
+    // Moves an already configured machine to its own thread, returning it once stopped
+    pub fn spawn(mut self) -> JoinHandle<IntcodeResult<Self>> {
+        thread::spawn(move || match self.run_blocking()? {
+            StopStatus::Halted => Ok(self),
+            _ => Err(IntcodeError::DidNotRunToCompletion),
+        })
+    }
+}
+
+pub fn spawn_machine<W: Word + Send + Sync + 'static>(
+    tape: Tape<W>,
+    input: Channel<W>,
+    output: Channel<W>,
+) -> JoinHandle<IntcodeResult<ChannelMachine<W>>> {
+    let memory = DenseMemory::from_tape(tape);
+    IntcodeMachine::with_memory(memory, input, output).spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_intcode_program, PagedMemory, TraceEvent};
+    use super::*;
+
+    #[test]
+    fn test_channel() {
+        let channel = Channel::new();
+        assert_eq!(channel.try_recv(), None);
+        assert_eq!(channel.recv_timeout(Duration::from_millis(1)), None);
+
+        let sender = channel.clone();
+        let handle = thread::spawn(move || {
+            for i in 0..3 {
+                sender.send(i);
+            }
+            sender.close();
+        });
+
+        let received: Vec<_> = std::iter::from_fn(|| channel.recv()).collect();
+        handle.join().unwrap();
+        assert_eq!(received, [0, 1, 2]);
+        assert!(channel.is_closed());
+        assert!(!channel.wait_readable());
+    }
+
+    #[test]
+    fn test_threaded_feedback_loop() {
+        // The 2019 day 7 feedback loop example
+        let tape: Tape = parse_intcode_program(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,\
+             4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        let phases = [9, 8, 7, 6, 5];
+        let channels: Vec<Channel> = phases.iter().map(|_| Channel::new()).collect();
+        for (channel, &phase) in channels.iter().zip(phases.iter()) {
+            channel.send(phase);
+        }
+        channels[0].send(0);
+
+        let handles: Vec<_> = (0..phases.len())
+            .map(|i| {
+                let input = channels[i].clone();
+                let output = channels[(i + 1) % phases.len()].clone();
+                spawn_machine(tape.clone(), input, output)
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+
+        assert_eq!(channels[0].drain(), [139629729]);
+    }
+
+    #[test]
+    fn test_spawn_configured() {
+        fn assert_send<T: Send>() {}
+        assert_send::<IntcodeMachine<isize, DenseMemory, Channel<isize>>>();
+        assert_send::<IntcodeMachine<isize, PagedMemory, Channel<isize>>>();
+
+        let tape: Tape = parse_intcode_program("3,9,1001,9,1,9,4,9,99,0");
+        let memory = DenseMemory::from_tape(tape);
+        let mut machine = IntcodeMachine::with_memory(memory, Channel::new(), Channel::new());
+        machine.set_instruction_limit(Some(100));
+        machine.set_tracer(|_: &TraceEvent| ());
+        machine.input.send(41);
+
+        let output = machine.output.clone();
+        let machine = machine.spawn().join().unwrap().unwrap();
+        assert_eq!(output.drain(), [42]);
+        assert_eq!(machine.instruction_count(), 4);
+    }
+
+    #[test]
+    fn test_resume_from_breakpoint() {
+        let tape: Tape = parse_intcode_program("104,1,104,2,99");
+        let memory = DenseMemory::from_tape(tape);
+        let output = Channel::new();
+        let mut machine = IntcodeMachine::with_memory(memory, Channel::new(), output.clone());
+        machine.add_breakpoint(2);
+
+        let consumer = thread::spawn(move || std::iter::from_fn(|| output.recv()).collect());
+        assert_eq!(machine.run_blocking().unwrap(), StopStatus::Breakpoint(2));
+        assert!(!machine.output.is_closed());
+        assert_eq!(machine.run_blocking().unwrap(), StopStatus::Halted);
+        let received: Vec<isize> = consumer.join().unwrap();
+        assert_eq!(received, [1, 2]);
+    }
+
+    #[test]
+    fn test_error_closes_output() {
+        // The consumer would wait forever if the failing producer left it open
+        let middle: Channel = Channel::new();
+        let output = Channel::new();
+        let producer = spawn_machine(
+            parse_intcode_program("104,1,42"),
+            Channel::new(),
+            middle.clone(),
+        );
+        let consumer = spawn_machine(
+            parse_intcode_program("3,7,4,7,1105,1,0,0"),
+            middle.clone(),
+            output.clone(),
+        );
+
+        assert!(matches!(
+            producer.join().unwrap(),
+            Err(IntcodeError::InvalidOpcodeOperation)
+        ));
+        assert!(middle.is_closed());
+        assert!(matches!(
+            consumer.join().unwrap(),
+            Err(IntcodeError::DidNotRunToCompletion)
+        ));
+        assert_eq!(output.drain(), [1]);
+        assert!(output.is_closed());
+    }
+
+    #[test]
+    fn test_channel_machine_snapshot() {
+        let tape: Tape = parse_intcode_program("3,0,4,0,99");
+        let input = Channel::new();
+        let output = Channel::new();
+        let memory = DenseMemory::from_tape(tape);
+        let mut machine = IntcodeMachine::with_memory(memory, input.clone(), output.clone());
+
+        input.send(42);
+        let snapshot = machine.snapshot();
+        assert_eq!(machine.run_blocking().unwrap(), StopStatus::Halted);
+        assert_eq!(output.drain(), [42]);
+
+        machine.restore(&snapshot);
+        assert_eq!(input.len(), 1);
+        let mut fork = machine.fork();
+        fork.run().unwrap();
+        assert_eq!(fork.output.drain(), [42]);
+        assert!(output.is_empty());
+    }
+}
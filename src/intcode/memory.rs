@@ -0,0 +1,142 @@
+use super::{Tape, Word};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+const PAGE_BITS: usize = 10;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+pub trait Memory<W: Word>: Clone + Debug {
+    const DEFAULT_MAX_ADDRESS: usize;
+
+    fn from_tape(tape: Tape<W>) -> Self;
+    fn read(&self, addr: usize) -> W;
+    fn write(&mut self, addr: usize, value: W);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DenseMemory<W = isize>(Tape<W>);
+
+impl<W> DenseMemory<W> {
+    pub fn tape(&self) -> &Tape<W> {
+        &self.0
+    }
+}
+
+impl<W: Word> Memory<W> for DenseMemory<W> {
+    // Big enough for any puzzle program, small enough to always allocate
+    const DEFAULT_MAX_ADDRESS: usize = (1 << 24) - 1;
+
+    fn from_tape(tape: Tape<W>) -> Self {
+        DenseMemory(tape)
+    }
+
+    fn read(&self, addr: usize) -> W {
+        match self.0.get(addr) {
+            Some(value) => value.clone(),
+            None => W::from_isize(0),
+        }
+    }
+
+    fn write(&mut self, addr: usize, value: W) {
+        if addr >= self.0.len() {
+            self.0.resize(addr + 1, W::from_isize(0));
+        }
+        self.0[addr] = value;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagedMemory<W = isize> {
+    pages: HashMap<usize, Vec<W>>,
+}
+
+impl<W> PagedMemory<W> {
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<W: Word> Memory<W> for PagedMemory<W> {
+    const DEFAULT_MAX_ADDRESS: usize = isize::MAX as usize;
+
+    fn from_tape(tape: Tape<W>) -> Self {
+        let mut memory = PagedMemory {
+            pages: HashMap::new(),
+        };
+        for (addr, value) in tape.into_iter().enumerate() {
+            memory.write(addr, value);
+        }
+        memory
+    }
+
+    fn read(&self, addr: usize) -> W {
+        match self.pages.get(&(addr >> PAGE_BITS)) {
+            Some(page) => page[addr % PAGE_SIZE].clone(),
+            None => W::from_isize(0),
+        }
+    }
+
+    fn write(&mut self, addr: usize, value: W) {
+        let page = self
+            .pages
+            .entry(addr >> PAGE_BITS)
+            .or_insert_with(|| vec![W::from_isize(0); PAGE_SIZE]);
+        page[addr % PAGE_SIZE] = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_intcode_program, IntcodeError, IntcodeMachine, Snapshot};
+    use super::*;
+
+    // Stores 7 at 10^12, reads it back and prints it
+    const FAR_WRITE: &str = "1101,3,4,1000000000000,4,1000000000000,99";
+
+    #[test]
+    fn test_paged_memory() {
+        let mut memory = PagedMemory::<isize>::from_tape(vec![1, 2, 3]);
+        assert_eq!(memory.read(2), 3);
+        assert_eq!(memory.read(1 << 40), 0);
+        memory.write(1 << 40, 5);
+        assert_eq!(memory.read(1 << 40), 5);
+        assert_eq!(memory.page_count(), 2);
+    }
+
+    #[test]
+    fn test_far_address() {
+        let tape: Tape = parse_intcode_program(FAR_WRITE);
+
+        let mut machine = IntcodeMachine::new(tape.clone());
+        assert!(matches!(
+            machine.run(),
+            Err(IntcodeError::AddressOutOfRange)
+        ));
+
+        let mut machine = IntcodeMachine::new_paged(tape.clone());
+        machine.run_to_completion().unwrap();
+        assert_eq!(*machine.output.borrow(), [7]);
+        assert_eq!(machine.memory().page_count(), 2);
+
+        let snapshot: Snapshot<isize, PagedMemory> = machine.snapshot();
+        let restored: IntcodeMachine<isize, PagedMemory> = IntcodeMachine::from_snapshot(&snapshot);
+        assert_eq!(restored.memory(), machine.memory());
+
+        let mut machine = IntcodeMachine::new_paged(tape);
+        machine.set_max_address(1 << 20);
+        assert!(matches!(
+            machine.run(),
+            Err(IntcodeError::AddressOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn test_dense_reads_do_not_grow() {
+        let mut machine = IntcodeMachine::<isize>::new(vec![4, 1000, 99]);
+        machine.run_to_completion().unwrap();
+        assert_eq!(*machine.output.borrow(), [0]);
+        assert_eq!(machine.tape().len(), 3);
+    }
+}
@@ -0,0 +1,422 @@
+use super::{IntcodeMachine, IntcodeResult, StopStatus, Stream, Tape, Word};
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet<W = isize> {
+    pub from: usize,
+    pub to: usize,
+    pub payload: Vec<W>,
+}
+
+pub trait Router<W = isize> {
+    // Consumes complete messages from a node's output; leftovers stay queued
+    fn route(&mut self, from: usize, output: &mut Stream<W>) -> Vec<Packet<W>>;
+}
+
+impl<W, F: FnMut(usize, &mut Stream<W>) -> Vec<Packet<W>>> Router<W> for F {
+    fn route(&mut self, from: usize, output: &mut Stream<W>) -> Vec<Packet<W>> {
+        self(from, output)
+    }
+}
+
+// Node i feeds node i + 1; the last node's output leaves the network
+#[derive(Debug, Clone, Copy)]
+pub struct Chain;
+
+impl<W> Router<W> for Chain {
+    fn route(&mut self, from: usize, output: &mut Stream<W>) -> Vec<Packet<W>> {
+        if output.is_empty() {
+            return Vec::new();
+        }
+        vec![Packet {
+            from,
+            to: from + 1,
+            payload: output.drain(..).collect(),
+        }]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ring {
+    pub len: usize,
+}
+
+impl<W> Router<W> for Ring {
+    fn route(&mut self, from: usize, output: &mut Stream<W>) -> Vec<Packet<W>> {
+        if output.is_empty() {
+            return Vec::new();
+        }
+        vec![Packet {
+            from,
+            to: (from + 1) % self.len,
+            payload: output.drain(..).collect(),
+        }]
+    }
+}
+
+// Output is a sequence of [address, payload...] messages of a fixed size
+#[derive(Debug, Clone, Copy)]
+pub struct PacketSwitch {
+    pub packet_size: usize,
+}
+
+impl<W: Word> Router<W> for PacketSwitch {
+    fn route(&mut self, from: usize, output: &mut Stream<W>) -> Vec<Packet<W>> {
+        let mut packets = Vec::new();
+        while output.len() >= self.packet_size {
+            let mut message = output.drain(..self.packet_size);
+            let address = message.next().unwrap();
+            let to = address
+                .to_isize()
+                .and_then(|a| usize::try_from(a).ok())
+                .unwrap_or(usize::MAX);
+            packets.push(Packet {
+                from,
+                to,
+                payload: message.collect(),
+            });
+        }
+        packets
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    RunUntilBlocked,
+    RoundRobin { quantum: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkStatus {
+    Running,
+    AllHalted,
+    Deadlocked,
+    Idle,
+    // A node stopped on a breakpoint or watchpoint; running again resumes it
+    Stopped(usize, StopStatus),
+}
+
+#[derive(Debug)]
+struct Node<W> {
+    machine: IntcodeMachine<W>,
+    halted: bool,
+    blocked: bool,
+    idle_rounds: usize,
+}
+
+pub struct Network<W = isize> {
+    nodes: Vec<Node<W>>,
+    router: Box<dyn Router<W>>,
+    policy: Policy,
+    idle_input: Option<(W, usize)>,
+    external: VecDeque<Packet<W>>,
+}
+
+impl<W: fmt::Debug> fmt::Debug for Network<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("nodes", &self.nodes)
+            .field("policy", &self.policy)
+            .field("external", &self.external)
+            .finish()
+    }
+}
+
+impl<W: Word> Network<W> {
+    pub fn new(machines: Vec<IntcodeMachine<W>>, router: impl Router<W> + 'static) -> Self {
+        let nodes = machines
+            .into_iter()
+            .map(|machine| Node {
+                machine,
+                halted: false,
+                blocked: false,
+                idle_rounds: 0,
+            })
+            .collect();
+        Network {
+            nodes,
+            router: Box::new(router),
+            policy: Policy::RunUntilBlocked,
+            idle_input: None,
+            external: VecDeque::new(),
+        }
+    }
+
+    fn replicate(tape: &Tape<W>, len: usize) -> Vec<IntcodeMachine<W>> {
+        (0..len)
+            .map(|_| IntcodeMachine::new(tape.clone()))
+            .collect()
+    }
+
+    pub fn chain(tape: &Tape<W>, len: usize) -> Self {
+        Self::new(Self::replicate(tape, len), Chain)
+    }
+
+    pub fn ring(tape: &Tape<W>, len: usize) -> Self {
+        Self::new(Self::replicate(tape, len), Ring { len })
+    }
+
+    pub fn packet_switched(tape: &Tape<W>, len: usize, packet_size: usize) -> Self {
+        Self::new(Self::replicate(tape, len), PacketSwitch { packet_size })
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Nodes starved of input are fed `value`; once every node has starved for
+    // `threshold` consecutive rounds without sending anything the network is
+    // reported idle
+    pub fn with_idle_input(mut self, value: W, threshold: usize) -> Self {
+        self.idle_input = Some((value, threshold));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, node: usize) -> &IntcodeMachine<W> {
+        &self.nodes[node].machine
+    }
+
+    pub fn send(&mut self, node: usize, values: impl IntoIterator<Item = W>) {
+        let node = &mut self.nodes[node];
+        node.machine.input.borrow_mut().extend(values);
+        node.idle_rounds = 0;
+    }
+
+    fn deliver(&mut self, packet: Packet<W>) {
+        if packet.to < self.nodes.len() {
+            self.send(packet.to, packet.payload);
+        } else {
+            self.external.push_back(packet);
+        }
+    }
+
+    pub fn take_external(&mut self) -> Vec<Packet<W>> {
+        self.external.drain(..).collect()
+    }
+
+    // Returns the stop of a node paused on a breakpoint or watchpoint
+    fn run_node(&mut self, i: usize) -> IntcodeResult<Option<StopStatus>> {
+        let node = &mut self.nodes[i];
+        let status = match self.policy {
+            Policy::RunUntilBlocked => Some(node.machine.run()?),
+            Policy::RoundRobin { quantum } => {
+                let mut status = None;
+                for _ in 0..quantum {
+                    status = node.machine.step()?;
+                    if status.is_some() {
+                        break;
+                    }
+                }
+                status
+            }
+        };
+
+        node.halted = status == Some(StopStatus::Halted);
+        node.blocked = status == Some(StopStatus::BlockedOnInput);
+        if let (true, Some((value, _))) = (node.blocked, &self.idle_input) {
+            node.machine.input.borrow_mut().push_back(value.clone());
+            node.idle_rounds += 1;
+        }
+
+        let output = node.machine.output.clone();
+        let packets = self.router.route(i, &mut output.borrow_mut());
+        if !packets.is_empty() {
+            node.idle_rounds = 0;
+        }
+        for packet in packets {
+            self.deliver(packet);
+        }
+
+        Ok(status.filter(|s| matches!(s, StopStatus::Breakpoint(_) | StopStatus::Watchpoint(_))))
+    }
+
+    pub fn status(&self) -> NetworkStatus {
+        if self.nodes.iter().all(|n| n.halted) {
+            return NetworkStatus::AllHalted;
+        }
+
+        match &self.idle_input {
+            Some((_, threshold)) => {
+                if self
+                    .nodes
+                    .iter()
+                    .all(|n| n.halted || n.idle_rounds >= *threshold)
+                {
+                    return NetworkStatus::Idle;
+                }
+            }
+            None => {
+                let starved = |n: &Node<W>| n.blocked && n.machine.input.borrow().is_empty();
+                if self.nodes.iter().all(|n| n.halted || starved(n)) {
+                    return NetworkStatus::Deadlocked;
+                }
+            }
+        }
+
+        NetworkStatus::Running
+    }
+
+    pub fn step_round(&mut self) -> IntcodeResult<NetworkStatus> {
+        for i in 0..self.nodes.len() {
+            if self.nodes[i].halted {
+                continue;
+            }
+            if let Some(stop) = self.run_node(i)? {
+                return Ok(NetworkStatus::Stopped(i, stop));
+            }
+        }
+        Ok(self.status())
+    }
+
+    pub fn run(&mut self) -> IntcodeResult<NetworkStatus> {
+        loop {
+            match self.step_round()? {
+                NetworkStatus::Running => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{assemble, parse_intcode_program};
+    use super::*;
+
+    // The 2019 day 7 examples
+    const LINEAR_EXAMPLE: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+    const FEEDBACK_EXAMPLE: &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,\
+                                    4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+    fn with_phases(mut network: Network, phases: &[isize]) -> Network {
+        for (i, &phase) in phases.iter().enumerate() {
+            network.send(i, [phase]);
+        }
+        network.send(0, [0]);
+        network
+    }
+
+    #[test]
+    fn test_chain() {
+        let tape = parse_intcode_program(LINEAR_EXAMPLE);
+        let mut network = with_phases(Network::chain(&tape, 5), &[4, 3, 2, 1, 0]);
+        assert_eq!(network.run().unwrap(), NetworkStatus::AllHalted);
+
+        let external = network.take_external();
+        assert_eq!(external.len(), 1);
+        assert_eq!(external[0].from, 4);
+        assert_eq!(external[0].payload, [43210]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let tape = parse_intcode_program(FEEDBACK_EXAMPLE);
+        for policy in [Policy::RunUntilBlocked, Policy::RoundRobin { quantum: 3 }] {
+            let network = Network::ring(&tape, 5).with_policy(policy);
+            let mut network = with_phases(network, &[9, 8, 7, 6, 5]);
+            assert_eq!(network.run().unwrap(), NetworkStatus::AllHalted);
+            assert_eq!(*network.machine(0).input.borrow(), [139629729]);
+        }
+    }
+
+    #[test]
+    fn test_idle_while_sending() {
+        // Sends a packet out for each of its first five idle reads
+        let tape = assemble(
+            "
+            poll:   in [x]
+                    lt [n], #5, [t]
+                    jf [t], #poll
+                    add [n], #1, [n]
+                    out #99
+                    out [n]
+                    jt #1, #poll
+            x:      .data 0
+            n:      .data 0
+            t:      .data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::packet_switched(&tape, 1, 2).with_idle_input(-1, 2);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Idle);
+        let payloads: Vec<_> = network
+            .take_external()
+            .into_iter()
+            .map(|p| p.payload[0])
+            .collect();
+        assert_eq!(payloads, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let tape = parse_intcode_program(LINEAR_EXAMPLE);
+        let mut machines = Network::replicate(&tape, 5);
+        machines[2].add_breakpoint(12);
+        let mut network = with_phases(Network::new(machines, Chain), &[4, 3, 2, 1, 0]);
+
+        assert_eq!(
+            network.run().unwrap(),
+            NetworkStatus::Stopped(2, StopStatus::Breakpoint(12))
+        );
+        assert_eq!(network.run().unwrap(), NetworkStatus::AllHalted);
+        assert_eq!(network.take_external()[0].payload, [43210]);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let tape = assemble("in [0]\nhlt").unwrap();
+        let mut network = Network::ring(&tape, 3);
+        network.send(1, [7]);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Deadlocked);
+        assert!(network.take_external().is_empty());
+    }
+
+    #[test]
+    fn test_packet_switch() {
+        // Each node sends [its input + 1] to node (its input + 1), or to 99
+        // once the value reaches 3; idle nodes read -1 and keep polling
+        let tape = assemble(
+            "
+            poll:   in [x]
+                    lt [x], #0, [t]
+                    jt [t], #poll
+                    add [x], #1, [x]
+                    eq [x], #3, [t]
+                    jf [t], #send
+                    out #99
+                    jt #1, #payload
+            send:   out [x]
+            payload: out [x]
+                    jt #1, #poll
+            x:      .data 0
+            t:      .data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::packet_switched(&tape, 3, 2).with_idle_input(-1, 2);
+        network.send(0, [0]);
+        assert_eq!(network.run().unwrap(), NetworkStatus::Idle);
+
+        let external = network.take_external();
+        assert_eq!(
+            external,
+            [Packet {
+                from: 2,
+                to: 99,
+                payload: vec![3],
+            }]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use super::trace::{TraceEvent, TraceSink};
+use super::{Opcode, Operation, Word};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+    pub instructions: usize,
+    pub hits: u64,
+}
+
+impl BasicBlock {
+    pub fn executed(&self) -> u64 {
+        self.hits * self.instructions as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Profiler<W = isize> {
+    hits: BTreeMap<usize, u64>,
+    opcodes: BTreeMap<usize, Opcode<W>>,
+    leaders: BTreeSet<usize>,
+    last: Option<(usize, bool)>,
+}
+
+fn ends_block<W>(opcode: &Opcode<W>) -> bool {
+    matches!(
+        opcode.operation,
+        Operation::JumpTrue | Operation::JumpFalse | Operation::Halt
+    )
+}
+
+impl<W: Word> TraceSink<W> for Profiler<W> {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        *self.hits.entry(event.pc).or_insert(0) += 1;
+        if self.opcodes.get(&event.pc) != Some(&event.opcode) {
+            self.opcodes.insert(event.pc, event.opcode.clone());
+        }
+
+        // A block starts wherever control did not simply fall through
+        match self.last {
+            Some((fallthrough, false)) if fallthrough == event.pc => (),
+            _ => {
+                self.leaders.insert(event.pc);
+            }
+        }
+        self.last = Some((event.next_addr(), ends_block(&event.opcode)));
+    }
+}
+
+impl<W> Default for Profiler<W> {
+    fn default() -> Self {
+        Profiler {
+            hits: BTreeMap::new(),
+            opcodes: BTreeMap::new(),
+            leaders: BTreeSet::new(),
+            last: None,
+        }
+    }
+}
+
+impl<W: Word> Profiler<W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.hits.values().sum()
+    }
+
+    pub fn hits(&self, pc: usize) -> u64 {
+        self.hits.get(&pc).copied().unwrap_or(0)
+    }
+
+    pub fn histogram(&self) -> Vec<(usize, u64)> {
+        let mut histogram: Vec<_> = self.hits.iter().map(|(&pc, &hits)| (pc, hits)).collect();
+        histogram.sort_by_key(|&(pc, hits)| (std::cmp::Reverse(hits), pc));
+        histogram
+    }
+
+    pub fn basic_blocks(&self) -> Vec<BasicBlock> {
+        let mut blocks = Vec::new();
+
+        for &start in self.leaders.iter() {
+            let mut addr = start;
+            let mut instructions = 0;
+            while let Some(opcode) = self.opcodes.get(&addr) {
+                instructions += 1;
+                addr += 1 + opcode.operands.len();
+                if ends_block(opcode) || self.leaders.contains(&addr) {
+                    break;
+                }
+            }
+
+            blocks.push(BasicBlock {
+                start,
+                end: addr,
+                instructions,
+                hits: self.hits(start),
+            });
+        }
+
+        blocks
+    }
+
+    pub fn hot_blocks(&self) -> Vec<BasicBlock> {
+        let mut blocks = self.basic_blocks();
+        blocks.sort_by_key(|b| (std::cmp::Reverse(b.executed()), b.start));
+        blocks
+    }
+
+    pub fn report(&self, top: usize) -> String {
+        let total = self.total().max(1) as f64;
+        let mut report = String::new();
+
+        writeln!(report, "{} instructions executed", self.total()).unwrap();
+        writeln!(report, "hottest instructions:").unwrap();
+        for (pc, hits) in self.histogram().into_iter().take(top) {
+            let percent = 100.0 * hits as f64 / total;
+            writeln!(
+                report,
+                "{:>5}: {:<32} {:>10} {:>6.2}%",
+                pc,
+                self.opcodes[&pc].to_string(),
+                hits,
+                percent
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "hottest basic blocks:").unwrap();
+        for block in self.hot_blocks().into_iter().take(top) {
+            let percent = 100.0 * block.executed() as f64 / total;
+            writeln!(
+                report,
+                "{}..{}: {} hits x {} instructions ({:.2}%)",
+                block.start, block.end, block.hits, block.instructions, percent
+            )
+            .unwrap();
+
+            let mut addr = block.start;
+            while addr < block.end {
+                let opcode = &self.opcodes[&addr];
+                writeln!(report, "  {:>5}: {}", addr, opcode).unwrap();
+                addr += 1 + opcode.operands.len();
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{assemble, IntcodeMachine};
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_profile_loop() {
+        let tape = assemble(
+            "
+                    add #0, #0, [i]
+            loop:   add [i], #1, [i]
+                    eq [i], #10, [done]
+                    jf [done], #loop
+                    hlt
+            i:      .data 0
+            done:   .data 0
+            ",
+        )
+        .unwrap();
+
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        let mut machine = IntcodeMachine::new(tape);
+        machine.set_tracer(profiler.clone());
+        machine.run_to_completion().unwrap();
+
+        let profiler = profiler.lock().unwrap();
+        assert_eq!(profiler.total(), 1 + 3 * 10 + 1);
+        assert_eq!(profiler.total(), machine.instruction_count() as u64);
+        assert_eq!(profiler.histogram()[0], (4, 10));
+
+        let blocks = profiler.basic_blocks();
+        let summary: Vec<_> = blocks
+            .iter()
+            .map(|b| (b.start, b.end, b.instructions, b.hits))
+            .collect();
+        assert_eq!(summary, [(0, 4, 1, 1), (4, 15, 3, 10), (15, 16, 1, 1)]);
+        assert_eq!(profiler.hot_blocks()[0].start, 4);
+
+        let report = profiler.report(3);
+        assert!(report.contains("4..15: 10 hits x 3 instructions"));
+        assert!(report.contains("   12: jf [17], #4"));
+    }
+}
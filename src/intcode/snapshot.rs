@@ -0,0 +1,140 @@
+use super::{DenseMemory, IntcodeIo, IntcodeMachine, Memory, Stream, Word};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot<W = isize, M = DenseMemory<W>> {
+    pub memory: M,
+    pub pc: isize,
+    pub bp: isize,
+    pub input: Stream<W>,
+    pub output: Stream<W>,
+    pub instruction_count: usize,
+    #[serde(default)]
+    pub halted: bool,
+}
+
+impl<W: Serialize + DeserializeOwned, M: Serialize + DeserializeOwned> Snapshot<W, M> {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+impl<W: Word, M: Memory<W>, S: IntcodeIo<W>> IntcodeMachine<W, M, S> {
+    pub fn snapshot(&self) -> Snapshot<W, M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            pc: self.pc,
+            bp: self.bp,
+            input: self.input.contents(),
+            output: self.output.contents(),
+            instruction_count: self.instruction_count,
+            halted: self.halted,
+        }
+    }
+
+    // Stream contents are replaced in place, so anyone sharing them stays connected
+    pub fn restore(&mut self, snapshot: &Snapshot<W, M>) {
+        self.memory.clone_from(&snapshot.memory);
+        self.decode_cache.clear();
+        self.pc = snapshot.pc;
+        self.bp = snapshot.bp;
+        self.input.set_contents(&snapshot.input);
+        self.output.set_contents(&snapshot.output);
+        self.instruction_count = snapshot.instruction_count;
+        self.halted = snapshot.halted;
+        self.resume_pc = None;
+    }
+
+    pub fn from_snapshot(snapshot: &Snapshot<W, M>) -> Self {
+        let mut machine = IntcodeMachine::with_memory(
+            snapshot.memory.clone(),
+            S::from_stream(snapshot.input.clone()),
+            S::from_stream(snapshot.output.clone()),
+        );
+        machine.restore(snapshot);
+        machine
+    }
+
+    // Deep copy with private streams; breakpoints and tracers are not carried over,
+    // decoded instructions are
+    pub fn fork(&self) -> Self {
+        let mut machine = Self::from_snapshot(&self.snapshot());
+        machine.decode_cache.clone_from(&self.decode_cache);
+        machine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{assemble, StopStatus};
+    use super::*;
+
+    fn echo_machine() -> IntcodeMachine {
+        let tape = assemble(
+            "
+            loop:   in [x]
+                    mul [x], #2, [x]
+                    out [x]
+                    jt #1, #loop
+            x:      .data 0
+            ",
+        )
+        .unwrap();
+        IntcodeMachine::new(tape)
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut machine = echo_machine();
+        machine.input.borrow_mut().extend([1, 2]);
+        let input = machine.input.clone();
+        let snapshot = machine.snapshot();
+
+        assert_eq!(machine.run().unwrap(), StopStatus::BlockedOnInput);
+        assert_eq!(machine.output.borrow().len(), 2);
+
+        machine.restore(&snapshot);
+        assert_eq!(machine.snapshot(), snapshot);
+        assert_eq!(input.borrow().len(), 2);
+        assert!(machine.output.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_fork() {
+        let machine = echo_machine();
+        machine.input.borrow_mut().push_back(5);
+        let mut fork = machine.fork();
+
+        fork.input.borrow_mut().push_back(7);
+        fork.run().unwrap();
+        assert_eq!(*fork.output.borrow(), [10, 14]);
+        assert_eq!(machine.input.borrow().len(), 1);
+        assert!(machine.output.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut machine = echo_machine();
+        machine.input.borrow_mut().push_back(3);
+        machine.step().unwrap();
+
+        let path = std::env::temp_dir().join(format!("intcode_snapshot_{}", std::process::id()));
+        machine.snapshot().save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, machine.snapshot());
+        let mut restored: IntcodeMachine = IntcodeMachine::from_snapshot(&loaded);
+        restored.input.borrow_mut().push_back(4);
+        restored.run().unwrap();
+        assert_eq!(*restored.output.borrow(), [6, 8]);
+    }
+}
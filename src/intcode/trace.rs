@@ -0,0 +1,63 @@
+use super::{Opcode, Word};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent<W = isize> {
+    pub pc: usize,
+    pub opcode: Opcode<W>,
+    pub loads: Vec<W>,
+    pub store: Option<(usize, W)>,
+}
+
+impl<W> TraceEvent<W> {
+    pub fn next_addr(&self) -> usize {
+        self.pc + 1 + self.opcode.operands.len()
+    }
+}
+
+impl<W: Word> fmt::Display for TraceEvent<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opcode = self.opcode.to_string();
+        write!(f, "{:>5}: {:<32} ;", self.pc, opcode)?;
+        for load in self.loads.iter() {
+            write!(f, " {}", load)?;
+        }
+        if let Some((addr, value)) = &self.store {
+            write!(f, " -> [{}]={}", addr, value)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait TraceSink<W = isize> {
+    fn record(&mut self, event: &TraceEvent<W>);
+}
+
+impl<W: Clone> TraceSink<W> for Vec<TraceEvent<W>> {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        self.push(event.clone());
+    }
+}
+
+impl<W, F: FnMut(&TraceEvent<W>)> TraceSink<W> for F {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        self(event)
+    }
+}
+
+// Lets the caller keep a handle on the sink to inspect it after running
+impl<W, T: TraceSink<W>> TraceSink<W> for Arc<Mutex<T>> {
+    fn record(&mut self, event: &TraceEvent<W>) {
+        self.lock().unwrap().record(event)
+    }
+}
+
+// Send so that a traced machine can still be moved to another thread
+pub(crate) struct Tracer<W>(pub Box<dyn TraceSink<W> + Send>);
+
+impl<W> fmt::Debug for Tracer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracer")
+    }
+}
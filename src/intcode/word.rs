@@ -0,0 +1,67 @@
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+pub trait Word: Clone + Debug + Display + PartialEq + PartialOrd + FromStr {
+    fn from_isize(value: isize) -> Self;
+    fn to_isize(&self) -> Option<isize>;
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::from_isize(0)
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < Self::from_isize(0)
+    }
+}
+
+macro_rules! impl_primitive_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn from_isize(value: isize) -> Self {
+                    value as $t
+                }
+
+                fn to_isize(&self) -> Option<isize> {
+                    isize::try_from(*self).ok()
+                }
+
+                fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *rhs)
+                }
+
+                fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive_word!(isize, i64, i128);
+
+impl Word for BigInt {
+    fn from_isize(value: isize) -> Self {
+        BigInt::from(value)
+    }
+
+    fn to_isize(&self) -> Option<isize> {
+        ToPrimitive::to_isize(self)
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+}
@@ -0,0 +1,314 @@
+use crate::aoc_input::{InputError, InputProvider, InputResult};
+use crate::calendar::unlock_ts;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Star {
+    pub get_star_ts: u64,
+    #[serde(default)]
+    pub star_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Member {
+    pub id: u64,
+    pub name: Option<String>,
+    pub stars: u32,
+    pub local_score: u64,
+    #[serde(default)]
+    pub global_score: u64,
+    #[serde(default)]
+    pub last_star_ts: u64,
+    #[serde(default)]
+    pub completion_day_level: BTreeMap<u8, BTreeMap<u8, Star>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Leaderboard {
+    pub owner_id: u64,
+    pub event: String,
+    pub members: HashMap<String, Member>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DaySolve {
+    pub day: u8,
+    pub part1: Option<Duration>,
+    pub part2: Option<Duration>,
+}
+
+impl DaySolve {
+    pub fn delta(&self) -> Option<Duration> {
+        Some(self.part2? - self.part1?)
+    }
+}
+
+impl Member {
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("(anonymous user #{})", self.id),
+        }
+    }
+
+    pub fn star_ts(&self, day: u8, part: u8) -> Option<u64> {
+        Some(self.completion_day_level.get(&day)?.get(&part)?.get_star_ts)
+    }
+
+    pub fn solve_times(&self, year: u16) -> Vec<DaySolve> {
+        self.completion_day_level
+            .keys()
+            .map(|&day| {
+                let unlock = unlock_ts(year, day);
+                let elapsed = |part| {
+                    let ts = self.star_ts(day, part)?;
+                    Some(Duration::from_secs(ts.saturating_sub(unlock)))
+                };
+                DaySolve {
+                    day,
+                    part1: elapsed(1),
+                    part2: elapsed(2),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Leaderboard {
+    pub fn parse(json: &str) -> InputResult<Self> {
+        serde_json::from_str(json).map_err(InputError::Json)
+    }
+
+    pub fn year(&self) -> Option<u16> {
+        self.event.parse().ok()
+    }
+
+    pub fn members_by_score(&self) -> Vec<&Member> {
+        let mut members: Vec<_> = self.members.values().collect();
+        members.sort_by_key(|m| (std::cmp::Reverse(m.local_score), m.id));
+        members
+    }
+
+    pub fn recompute_local_scores(&self) -> BTreeMap<u64, u64> {
+        let member_count = self.members.len() as u64;
+        let mut scores: BTreeMap<u64, u64> = self.members.values().map(|m| (m.id, 0)).collect();
+
+        let days = self
+            .members
+            .values()
+            .flat_map(|m| m.completion_day_level.keys().copied())
+            .max()
+            .unwrap_or(0);
+
+        for day in 1..=days {
+            for part in 1..=2 {
+                let mut finishers: Vec<_> = self
+                    .members
+                    .values()
+                    .filter_map(|m| Some((m.star_ts(day, part)?, m.id)))
+                    .collect();
+                finishers.sort();
+
+                for (rank, (_, id)) in finishers.into_iter().enumerate() {
+                    *scores.get_mut(&id).unwrap() += member_count - rank as u64;
+                }
+            }
+        }
+
+        scores
+    }
+
+    pub fn solve_report(&self) -> SolveReport {
+        let year = self.year().unwrap_or(0);
+        let rows = self
+            .members_by_score()
+            .into_iter()
+            .map(|m| (m.display_name(), m.solve_times(year)))
+            .collect();
+        SolveReport { rows }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveReport {
+    pub rows: Vec<(String, Vec<DaySolve>)>,
+}
+
+fn format_duration(d: Option<Duration>) -> String {
+    match d {
+        None => "-".to_string(),
+        Some(d) => {
+            let secs = d.as_secs();
+            format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+    }
+}
+
+impl fmt::Display for SolveReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>3} {:>10} {:>10} {:>10}",
+            "member", "day", "part 1", "part 2", "delta"
+        )?;
+        for (name, solves) in self.rows.iter() {
+            for solve in solves {
+                writeln!(
+                    f,
+                    "{:<24} {:>3} {:>10} {:>10} {:>10}",
+                    name,
+                    solve.day,
+                    format_duration(solve.part1),
+                    format_duration(solve.part2),
+                    format_duration(solve.delta())
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn cache_name(year: u16, id: u64) -> String {
+    format!("leaderboard_{}_{}.json", year, id)
+}
+
+fn is_fresh(fetched_at: u64) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    now.saturating_sub(fetched_at) < REFRESH_INTERVAL.as_secs()
+}
+
+pub fn fetch(provider: &InputProvider, year: u16, id: u64) -> InputResult<Leaderboard> {
+    let name = cache_name(year, id);
+    let cache = provider.cache();
+    let cached = cache.load(&name)?;
+
+    if let (Some(json), Some(meta)) = (&cached, cache.metadata(&name)?) {
+        if is_fresh(meta.fetched_at) {
+            return Leaderboard::parse(json);
+        }
+    }
+
+    let path = format!("/{}/leaderboard/private/view/{}.json", year, id);
+    let resp = match provider.get_path(&path) {
+        Ok(resp) => resp,
+        Err(InputError::Offline) if cached.is_some() => {
+            return Leaderboard::parse(&cached.unwrap())
+        }
+        Err(e) => return Err(e),
+    };
+
+    if !resp.body.trim_start().starts_with('{') {
+        return Err(InputError::UnexpectedContent);
+    }
+    let leaderboard = Leaderboard::parse(&resp.body)?;
+    cache.store(&name, &resp)?;
+    Ok(leaderboard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aoc_input::test_server::scratch_dir;
+    use crate::aoc_input::throttle::RateLimiter;
+    use crate::aoc_input::transport::{FakeTransport, HttpResponse};
+    use crate::aoc_input::SessionSource;
+    use std::sync::Arc;
+
+    fn example() -> Leaderboard {
+        Leaderboard::parse(include_str!("leaderboard_example.json")).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let leaderboard = example();
+        assert_eq!(leaderboard.year(), Some(2021));
+        assert_eq!(leaderboard.members.len(), 3);
+
+        let alice = &leaderboard.members["1"];
+        assert_eq!(alice.stars, 3);
+        assert_eq!(alice.star_ts(1, 2), Some(1638335400));
+        assert_eq!(alice.star_ts(2, 2), None);
+        assert_eq!(
+            leaderboard.members["2"].display_name(),
+            "(anonymous user #2)"
+        );
+
+        let order: Vec<_> = leaderboard
+            .members_by_score()
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(order, [1, 3, 2]);
+    }
+
+    #[test]
+    fn test_solve_times() {
+        let leaderboard = example();
+        let solves = leaderboard.members["1"].solve_times(2021);
+        assert_eq!(
+            solves,
+            [
+                DaySolve {
+                    day: 1,
+                    part1: Some(Duration::from_secs(300)),
+                    part2: Some(Duration::from_secs(600)),
+                },
+                DaySolve {
+                    day: 2,
+                    part1: Some(Duration::from_secs(1000)),
+                    part2: None,
+                },
+            ]
+        );
+        assert_eq!(solves[0].delta(), Some(Duration::from_secs(300)));
+        assert_eq!(solves[1].delta(), None);
+
+        let report = leaderboard.solve_report().to_string();
+        assert!(report.contains("alice"));
+        assert!(report.contains("0:05:00"));
+        assert_eq!(report.lines().count(), 1 + 2 + 1 + 1);
+    }
+
+    #[test]
+    fn test_recompute_local_scores() {
+        let leaderboard = example();
+        let scores = leaderboard.recompute_local_scores();
+        for member in leaderboard.members.values() {
+            assert_eq!(scores[&member.id], member.local_score);
+        }
+    }
+
+    #[test]
+    fn test_fetch_caches_for_refresh_interval() {
+        let url = "http://fake/2021/leaderboard/private/view/1.json";
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(url, HttpResponse::new(200, "<html>Log in</html>"));
+        transport.push_response(
+            url,
+            HttpResponse::new(200, include_str!("leaderboard_example.json")),
+        );
+
+        let provider = InputProvider::new()
+            .with_session(SessionSource::Key("cafe".to_string()))
+            .with_cache_dir(scratch_dir("leaderboard_fetch"))
+            .with_base_url("http://fake")
+            .with_rate_limiter(RateLimiter::disabled())
+            .with_transport(transport.clone());
+
+        assert!(matches!(
+            fetch(&provider, 2021, 1),
+            Err(InputError::UnexpectedContent)
+        ));
+        assert_eq!(fetch(&provider, 2021, 1).unwrap(), example());
+        assert_eq!(fetch(&provider, 2021, 1).unwrap(), example());
+        assert_eq!(transport.requests().len(), 2);
+    }
+}
@@ -1,8 +1,11 @@
 pub mod aoc_input;
+pub mod calendar;
 pub mod coordinates;
 pub mod digits;
 pub mod grid;
 pub mod intcode;
+pub mod leaderboard;
 pub mod num;
 pub mod parse;
+pub mod springscript;
 pub mod vec;
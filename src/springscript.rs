@@ -0,0 +1,760 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    T,
+    J,
+}
+
+impl Register {
+    pub const SENSORS: [Register; 9] = [
+        Register::A,
+        Register::B,
+        Register::C,
+        Register::D,
+        Register::E,
+        Register::F,
+        Register::G,
+        Register::H,
+        Register::I,
+    ];
+
+    // Distance ahead of the droid that a sensor register looks at, minus one
+    pub fn sensor(&self) -> Option<usize> {
+        Self::SENSORS.iter().position(|r| r == self)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        matches!(self, Register::T | Register::J)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Register {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let register = match s {
+            "T" => Register::T,
+            "J" => Register::J,
+            _ => {
+                let sensor = Self::SENSORS.iter().find(|r| r.to_string() == s);
+                *sensor.ok_or(())?
+            }
+        };
+        Ok(register)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    And,
+    Or,
+    Not,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::And => "AND",
+            Operation::Or => "OR",
+            Operation::Not => "NOT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub op: Operation,
+    pub src: Register,
+    pub dst: Register,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.op, self.src, self.dst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    pub fn sensor_count(&self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+
+    pub fn sensors(&self) -> &'static [Register] {
+        &Register::SENSORS[..self.sensor_count()]
+    }
+
+    fn readable(&self) -> impl Iterator<Item = Register> {
+        let sensors = self.sensors().iter().copied();
+        sensors.chain([Register::T, Register::J])
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Walk => write!(f, "WALK"),
+            Mode::Run => write!(f, "RUN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub instructions: Vec<Instruction>,
+    pub mode: Mode,
+}
+
+// One instruction per line followed by the mode, exactly as the droid expects it
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instruction in self.instructions.iter() {
+            writeln!(f, "{}", instruction)?;
+        }
+        writeln!(f, "{}", self.mode)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnknownOperation(String),
+    WrongOperandCount(usize),
+    InvalidRegister(String),
+    ReadOnlyRegister(Register),
+    UnavailableSensor(Register),
+    TooManyInstructions(usize),
+    MissingMode,
+    TrailingInstruction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_instruction(line: &str) -> Result<Instruction, ParseErrorKind> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let op = match words[0] {
+        "AND" => Operation::And,
+        "OR" => Operation::Or,
+        "NOT" => Operation::Not,
+        op => return Err(ParseErrorKind::UnknownOperation(op.to_string())),
+    };
+    if words.len() != 3 {
+        return Err(ParseErrorKind::WrongOperandCount(words.len() - 1));
+    }
+
+    let register = |s: &str| {
+        s.parse::<Register>()
+            .map_err(|_| ParseErrorKind::InvalidRegister(s.to_string()))
+    };
+    let src = register(words[1])?;
+    let dst = register(words[2])?;
+    if !dst.is_writable() {
+        return Err(ParseErrorKind::ReadOnlyRegister(dst));
+    }
+    Ok(Instruction { op, src, dst })
+}
+
+impl FromStr for Script {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut instructions = Vec::new();
+        let mut mode = None;
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |kind| ParseError { line: i + 1, kind };
+            if mode.is_some() {
+                return Err(error(ParseErrorKind::TrailingInstruction));
+            }
+
+            match line {
+                "WALK" => mode = Some(Mode::Walk),
+                "RUN" => mode = Some(Mode::Run),
+                _ => instructions.push((i + 1, parse_instruction(line).map_err(error)?)),
+            }
+        }
+
+        let line_count = s.lines().count();
+        let mode = mode.ok_or(ParseError {
+            line: line_count,
+            kind: ParseErrorKind::MissingMode,
+        })?;
+        if instructions.len() > MAX_INSTRUCTIONS {
+            return Err(ParseError {
+                line: instructions[MAX_INSTRUCTIONS].0,
+                kind: ParseErrorKind::TooManyInstructions(instructions.len()),
+            });
+        }
+        for (line, instruction) in instructions.iter() {
+            if !mode.readable().any(|r| r == instruction.src) {
+                return Err(ParseError {
+                    line: *line,
+                    kind: ParseErrorKind::UnavailableSensor(instruction.src),
+                });
+            }
+        }
+
+        Ok(Script {
+            instructions: instructions.into_iter().map(|(_, i)| i).collect(),
+            mode,
+        })
+    }
+}
+
+// Ground is true; index 0 is the tile the droid starts on
+pub type Hull = Vec<bool>;
+
+pub fn parse_hull(s: &str) -> Option<Hull> {
+    s.trim()
+        .chars()
+        .map(|c| match c {
+            '#' => Some(true),
+            '.' => Some(false),
+            _ => None,
+        })
+        .collect()
+}
+
+// The droid draws each step of its fall; the first frame has it at the start
+pub fn parse_failure(text: &str) -> Option<Hull> {
+    let mut lines = text.lines().skip_while(|line| !line.starts_with('@'));
+    lines.next()?;
+    parse_hull(lines.next()?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Survived,
+    Fell(usize),
+}
+
+// Bit i is set when the tile i + 1 ahead is ground; tiles past the end are ground
+fn sensor_bits(hull: &[bool], pos: usize, mode: Mode) -> u16 {
+    let mut bits = 0;
+    for i in 0..mode.sensor_count() {
+        if *hull.get(pos + 1 + i).unwrap_or(&true) {
+            bits |= 1 << i;
+        }
+    }
+    bits
+}
+
+fn walk(hull: &[bool], mut jumps: impl FnMut(usize) -> bool) -> Outcome {
+    let mut pos = 0;
+    while pos < hull.len() {
+        if !hull[pos] {
+            return Outcome::Fell(pos);
+        }
+        pos += if jumps(pos) { 4 } else { 1 };
+    }
+    Outcome::Survived
+}
+
+impl Script {
+    pub fn jumps(&self, sensors: &[bool]) -> bool {
+        let (mut t, mut j) = (false, false);
+        for instruction in self.instructions.iter() {
+            let src = match instruction.src {
+                Register::T => t,
+                Register::J => j,
+                r => *sensors.get(r.sensor().unwrap()).unwrap_or(&true),
+            };
+            let dst = match instruction.dst {
+                Register::T => &mut t,
+                _ => &mut j,
+            };
+            *dst = match instruction.op {
+                Operation::And => *dst && src,
+                Operation::Or => *dst || src,
+                Operation::Not => !src,
+            };
+        }
+        j
+    }
+
+    pub fn simulate(&self, hull: &[bool]) -> Outcome {
+        walk(hull, |pos| {
+            let bits = sensor_bits(hull, pos, self.mode);
+            let sensors: Vec<bool> = (0..9).map(|i| bits & (1 << i) != 0).collect();
+            self.jumps(&sensors)
+        })
+    }
+}
+
+// A disjunction of sensor readings; (sensor, true) holds when that tile is ground
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Clause(Vec<(usize, bool)>);
+
+impl Clause {
+    fn holds(&self, bits: u16) -> bool {
+        self.0
+            .iter()
+            .any(|&(sensor, ground)| (bits & (1 << sensor) != 0) == ground)
+    }
+
+    fn is_single_ground(&self) -> bool {
+        matches!(self.0[..], [(_, true)])
+    }
+
+    // Evaluates the clause into `dst`, which need not start out false
+    fn compile(&self, dst: Register, out: &mut Vec<Instruction>) {
+        let sensor = |s: usize| Register::SENSORS[s];
+        let holes: Vec<usize> = self.0.iter().filter(|l| !l.1).map(|l| l.0).collect();
+        let ground: Vec<usize> = self.0.iter().filter(|l| l.1).map(|l| l.0).collect();
+        let mut emit = |op, src| out.push(Instruction { op, src, dst });
+
+        // Any hole among several sensors is NOT (all of them ground)
+        match holes[..] {
+            [] => {
+                emit(Operation::Not, sensor(ground[0]));
+                emit(Operation::Not, dst);
+            }
+            [hole] => emit(Operation::Not, sensor(hole)),
+            [first, ref rest @ ..] => {
+                emit(Operation::Not, sensor(first));
+                emit(Operation::Not, dst);
+                for &hole in rest {
+                    emit(Operation::And, sensor(hole));
+                }
+                emit(Operation::Not, dst);
+            }
+        }
+        let skip = usize::from(holes.is_empty());
+        for &s in ground.iter().skip(skip) {
+            emit(Operation::Or, sensor(s));
+        }
+    }
+
+    // Instructions needed to AND the clause into J, going through T
+    fn cost(&self) -> usize {
+        let holes = self.0.iter().filter(|l| !l.1).count();
+        let ground = self.0.len() - holes;
+        match holes {
+            _ if self.is_single_ground() => 1,
+            0 | 1 => ground + 2,
+            _ => holes + ground + 3,
+        }
+    }
+
+    // Instructions needed when the clause is the first thing written to J
+    fn first_cost(&self) -> usize {
+        match self.0.iter().all(|l| l.1) {
+            true => self.0.len(),
+            false => self.cost() - 1,
+        }
+    }
+}
+
+// Writes the conjunction of the clauses into J, starting with the one that
+// gains the most from being written into J directly
+fn compile(mut clauses: Vec<Clause>) -> Vec<Instruction> {
+    let mut out = Vec::new();
+    let Some(first) =
+        (0..clauses.len()).max_by_key(|&i| clauses[i].cost() - clauses[i].first_cost())
+    else {
+        return out;
+    };
+    let first = clauses.remove(first);
+    if first.0.iter().all(|l| l.1) {
+        for &(s, _) in first.0.iter() {
+            out.push(Instruction {
+                op: Operation::Or,
+                src: Register::SENSORS[s],
+                dst: Register::J,
+            });
+        }
+    } else {
+        first.compile(Register::J, &mut out);
+    }
+
+    for clause in clauses {
+        if clause.is_single_ground() {
+            out.push(Instruction {
+                op: Operation::And,
+                src: Register::SENSORS[clause.0[0].0],
+                dst: Register::J,
+            });
+            continue;
+        }
+        clause.compile(Register::T, &mut out);
+        out.push(Instruction {
+            op: Operation::And,
+            src: Register::T,
+            dst: Register::J,
+        });
+    }
+    out
+}
+
+fn program_cost(clauses: &[&Clause]) -> usize {
+    let total: usize = clauses.iter().map(|c| c.cost()).sum();
+    let saving = clauses.iter().map(|c| c.cost() - c.first_cost()).max();
+    total - saving.unwrap_or(0)
+}
+
+const MAX_CLAUSE_LITERALS: usize = 3;
+
+fn candidate_clauses(mode: Mode) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    let mut round = vec![Clause(Vec::new())];
+    for _ in 0..MAX_CLAUSE_LITERALS {
+        let mut next = Vec::new();
+        for clause in round.iter() {
+            let start = clause.0.last().map_or(0, |l| l.0 + 1);
+            for sensor in start..mode.sensor_count() {
+                for ground in [false, true] {
+                    let mut literals = clause.0.clone();
+                    literals.push((sensor, ground));
+                    next.push(Clause(literals));
+                }
+            }
+        }
+        clauses.extend(next.iter().cloned());
+        round = next;
+    }
+    clauses
+}
+
+// Chooses clauses that hold at every jump and between them fail at every walk
+struct Cover<'a> {
+    clauses: Vec<(&'a Clause, u128)>,
+    walks: usize,
+    limit: usize,
+    best: Option<(usize, Vec<&'a Clause>)>,
+}
+
+impl<'a> Cover<'a> {
+    fn search(&mut self, chosen: &mut Vec<&'a Clause>, covered: u128) {
+        let cost = program_cost(chosen);
+        let limit = self.best.as_ref().map_or(self.limit, |b| b.0);
+        // Adding a clause never lowers the cost by more than its own saving
+        if cost.saturating_sub(2) >= limit {
+            return;
+        }
+
+        let uncovered = (0..self.walks).find(|&i| covered & (1 << i) == 0);
+        let Some(point) = uncovered else {
+            if cost < limit {
+                self.best = Some((cost, chosen.clone()));
+            }
+            return;
+        };
+
+        for i in 0..self.clauses.len() {
+            let (clause, mask) = self.clauses[i];
+            if mask & (1 << point) != 0 {
+                chosen.push(clause);
+                self.search(chosen, covered | mask);
+                chosen.pop();
+            }
+        }
+    }
+}
+
+fn cover(
+    candidates: &[Clause],
+    jumps: &[u16],
+    walks: &[u16],
+    limit: usize,
+) -> Option<Vec<Instruction>> {
+    // No walks to rule out means always jumping
+    if walks.is_empty() {
+        let always = Instruction {
+            op: Operation::Not,
+            src: Register::T,
+            dst: Register::J,
+        };
+        return match jumps.is_empty() {
+            true => Some(Vec::new()),
+            false => Some(vec![always]).filter(|_| limit > 1),
+        };
+    }
+    if walks.len() > 128 {
+        return None;
+    }
+
+    // Keep the cheapest clause for each distinct set of walks it rules out
+    let mut by_mask = HashMap::<u128, &Clause>::new();
+    for clause in candidates {
+        if !jumps.iter().all(|&bits| clause.holds(bits)) {
+            continue;
+        }
+        let mut mask = 0;
+        for (i, &bits) in walks.iter().enumerate() {
+            if !clause.holds(bits) {
+                mask |= 1 << i;
+            }
+        }
+        if mask == 0 {
+            continue;
+        }
+        let entry = by_mask.entry(mask).or_insert(clause);
+        if clause.cost() < entry.cost() {
+            *entry = clause;
+        }
+    }
+
+    let mut clauses: Vec<(&Clause, u128)> = by_mask.into_iter().map(|(m, c)| (c, m)).collect();
+    clauses.sort_by_key(|(c, m)| (c.cost(), std::cmp::Reverse(m.count_ones()), c.0.clone()));
+
+    let mut cover = Cover {
+        clauses,
+        walks: walks.len(),
+        limit,
+        best: None,
+    };
+    cover.search(&mut Vec::new(), 0);
+    let (_, chosen) = cover.best?;
+    Some(compile(chosen.into_iter().cloned().collect()))
+}
+
+// Whether any sequence of jumps crosses the hull at all
+fn crossable(hull: &[bool]) -> bool {
+    let mut reachable = vec![false; hull.len() + 4];
+    reachable[0] = true;
+    for pos in 0..hull.len() {
+        if reachable[pos] && hull[pos] {
+            reachable[pos + 1] = true;
+            reachable[pos + 4] = true;
+        }
+    }
+    reachable[hull.len()..].iter().any(|&r| r)
+}
+
+struct Synthesizer<'a> {
+    mode: Mode,
+    hulls: &'a [Hull],
+    candidates: Vec<Clause>,
+    decisions: HashMap<u16, bool>,
+    best: Option<Vec<Instruction>>,
+}
+
+impl Synthesizer<'_> {
+    // Runs the hulls with the decisions made so far, returning the first sensor
+    // reading that still needs one, or an error if a fully decided run falls
+    fn undecided(&self) -> Result<Option<u16>, ()> {
+        let mut first = None;
+        for hull in self.hulls {
+            let mut undecided = None;
+            let outcome = walk(hull, |pos| {
+                let bits = sensor_bits(hull, pos, self.mode);
+                match self.decisions.get(&bits) {
+                    Some(&jump) => jump,
+                    None => {
+                        undecided.get_or_insert(bits);
+                        false
+                    }
+                }
+            });
+            match undecided {
+                Some(bits) => {
+                    first.get_or_insert(bits);
+                }
+                None if outcome != Outcome::Survived => return Err(()),
+                None => (),
+            }
+        }
+        Ok(first)
+    }
+
+    fn search(&mut self) {
+        let bits = match self.undecided() {
+            Err(()) => return,
+            Ok(Some(bits)) => bits,
+            Ok(None) => {
+                let (jumps, walks): (Vec<_>, Vec<_>) =
+                    self.decisions.iter().partition(|(_, &jump)| jump);
+                let jumps: Vec<u16> = jumps.into_iter().map(|(&b, _)| b).collect();
+                let walks: Vec<u16> = walks.into_iter().map(|(&b, _)| b).collect();
+                let limit = self.best.as_ref().map_or(MAX_INSTRUCTIONS + 1, |b| b.len());
+                if let Some(program) = cover(&self.candidates, &jumps, &walks, limit) {
+                    self.best = Some(program);
+                }
+                return;
+            }
+        };
+
+        for jump in [false, true] {
+            self.decisions.insert(bits, jump);
+            self.search();
+        }
+        self.decisions.remove(&bits);
+    }
+}
+
+// Finds a short script that gets the droid across every given hull, by trying
+// each way of deciding when to jump and covering the decisions with a CNF formula
+pub fn synthesize(mode: Mode, hulls: &[Hull]) -> Option<Script> {
+    if !hulls.iter().all(|hull| crossable(hull)) {
+        return None;
+    }
+
+    let mut synthesizer = Synthesizer {
+        mode,
+        hulls,
+        candidates: candidate_clauses(mode),
+        decisions: HashMap::new(),
+        best: None,
+    };
+    synthesizer.search();
+    let instructions = synthesizer.best?;
+    Some(Script { instructions, mode })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "NOT D J\nWALK\n";
+
+    fn hulls(patterns: &[&str]) -> Vec<Hull> {
+        patterns.iter().map(|p| parse_hull(p).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_print() {
+        let script: Script = EXAMPLE.parse().unwrap();
+        assert_eq!(
+            script.instructions,
+            [Instruction {
+                op: Operation::Not,
+                src: Register::D,
+                dst: Register::J
+            }]
+        );
+        assert_eq!(script.mode, Mode::Walk);
+        assert_eq!(script.to_string(), EXAMPLE);
+
+        let error = |s: &str| s.parse::<Script>().unwrap_err().kind;
+        assert_eq!(
+            error("XOR A J\nWALK"),
+            ParseErrorKind::UnknownOperation("XOR".to_string())
+        );
+        assert_eq!(error("AND A\nWALK"), ParseErrorKind::WrongOperandCount(1));
+        assert_eq!(
+            error("AND A X\nWALK"),
+            ParseErrorKind::InvalidRegister("X".to_string())
+        );
+        assert_eq!(
+            error("AND T A\nWALK"),
+            ParseErrorKind::ReadOnlyRegister(Register::A)
+        );
+        assert_eq!(
+            error("AND E J\nWALK"),
+            ParseErrorKind::UnavailableSensor(Register::E)
+        );
+        assert_eq!(error("AND A J"), ParseErrorKind::MissingMode);
+        assert_eq!(error("RUN\nAND A J"), ParseErrorKind::TrailingInstruction);
+        assert_eq!(error(&"OR A J\n".repeat(16)), ParseErrorKind::MissingMode);
+        assert_eq!(
+            error(&("OR A J\n".repeat(16) + "RUN")),
+            ParseErrorKind::TooManyInstructions(16)
+        );
+    }
+
+    #[test]
+    fn test_simulate() {
+        // Jumping at the last moment lands in the second gap
+        let script: Script = "NOT A J\nWALK".parse().unwrap();
+        let hull = parse_hull("#####.#..########").unwrap();
+        assert_eq!(script.simulate(&hull), Outcome::Fell(8));
+
+        let script: Script = "NOT A J\nNOT C T\nOR T J\nAND D J\nWALK".parse().unwrap();
+        assert_eq!(script.simulate(&hull), Outcome::Survived);
+    }
+
+    #[test]
+    fn test_parse_failure() {
+        let text = "\nWalking...\n\nDidn't make it across:\n\n\
+                    .................\n\
+                    .................\n\
+                    @................\n\
+                    #####.###########\n\n\
+                    .................\n\
+                    .................\n\
+                    .@...............\n\
+                    #####.###########\n";
+        assert_eq!(parse_failure(text), parse_hull("#####.###########"));
+        assert_eq!(parse_failure("Walking...\n"), None);
+    }
+
+    #[test]
+    fn test_synthesize_walk() {
+        let hulls = hulls(&[
+            "#####.###########",
+            "#####..#.########",
+            "#####...#########",
+        ]);
+        let script = synthesize(Mode::Walk, &hulls).unwrap();
+        assert!(script.instructions.len() <= 5);
+        for hull in hulls.iter() {
+            assert_eq!(script.simulate(hull), Outcome::Survived);
+        }
+    }
+
+    #[test]
+    fn test_synthesize_run() {
+        let hulls = hulls(&[
+            "#####.###########",
+            "#####...#########",
+            "#####..#.########",
+            "#####.#..########",
+            "#####.##.##.#.###",
+            "#####.#.##..#####",
+            "#####.#.#...#.###",
+        ]);
+        let script = synthesize(Mode::Run, &hulls).unwrap();
+        for hull in hulls.iter() {
+            assert_eq!(script.simulate(hull), Outcome::Survived);
+        }
+
+        // Every landing spot past the first gap is a hole
+        let impossible = parse_hull("#####.##.#..#####").unwrap();
+        assert!(!crossable(&impossible));
+        assert_eq!(synthesize(Mode::Run, &[impossible]), None);
+    }
+}